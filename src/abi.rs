@@ -0,0 +1,68 @@
+use anyhow::{bail, Context, Result};
+use ethers::abi::Token;
+use ethers::types::I256;
+use std::path::Path;
+
+/// The JSON items of an ABI file, named after the file without its `.json` extension.
+pub struct Abi {
+    pub name: String,
+    pub items: Vec<serde_json::Value>,
+}
+
+/// Reads every `*.json` file of a directory, either a plain ABI array or a compiler artifact
+/// with an `abi` field, in file name order.
+pub fn read_abi_dir(abi_dir: &Path) -> Result<Vec<Abi>> {
+    let mut paths = std::fs::read_dir(abi_dir)
+        .with_context(|| format!("Failed to read ABI directory {}", abi_dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "json"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read ABI {}", path.display()))?;
+            let json: serde_json::Value = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+
+            let items = match json {
+                serde_json::Value::Array(items) => items,
+                serde_json::Value::Object(mut artifact) => match artifact.remove("abi") {
+                    Some(serde_json::Value::Array(items)) => items,
+                    _ => bail!("No ABI array in {}", path.display()),
+                },
+                _ => bail!("No ABI array in {}", path.display()),
+            };
+
+            Ok(Abi {
+                name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+                items,
+            })
+        })
+        .collect()
+}
+
+/// Renders a decoded value for a CSV cell: scalars as text and arrays and tuples as JSON.
+pub fn format_token(token: &Token) -> String {
+    match token_to_json(token) {
+        serde_json::Value::String(value) => value,
+        value => value.to_string(),
+    }
+}
+
+/// Integers become decimal strings so that JSON readers do not round them.
+pub fn token_to_json(token: &Token) -> serde_json::Value {
+    match token {
+        Token::Address(address) => format!("{:#x}", address).into(),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => format!("0x{}", hex::encode(bytes)).into(),
+        Token::Int(value) => I256::from_raw(*value).to_string().into(),
+        Token::Uint(value) => value.to_string().into(),
+        Token::Bool(value) => (*value).into(),
+        Token::String(value) => value.clone().into(),
+        Token::Array(values) | Token::FixedArray(values) | Token::Tuple(values) => {
+            values.iter().map(token_to_json).collect::<Vec<_>>().into()
+        }
+    }
+}
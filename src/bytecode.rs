@@ -0,0 +1,417 @@
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+
+const ERC20_SIGHASHES: [&str; 6] = [
+    "0x18160ddd", // totalSupply()
+    "0x70a08231", // balanceOf(address)
+    "0xa9059cbb", // transfer(address,uint256)
+    "0x23b872dd", // transferFrom(address,address,uint256)
+    "0x095ea7b3", // approve(address,uint256)
+    "0xdd62ed3e", // allowance(address,address)
+];
+
+const ERC721_SIGHASHES: [&str; 3] = [
+    "0x70a08231", // balanceOf(address)
+    "0x6352211e", // ownerOf(uint256)
+    "0x095ea7b3", // approve(address,uint256)
+];
+
+const ERC721_TRANSFER_SIGHASHES: [&str; 2] = [
+    "0xa9059cbb", // transfer(address,uint256)
+    "0x23b872dd", // transferFrom(address,address,uint256)
+];
+
+/// Collects the operands of every PUSH4 instruction in runtime bytecode, which is where the
+/// Solidity and Vyper dispatchers keep the function selectors.
+///
+/// The bytecode is walked instruction by instruction so that bytes inside the immediate data
+/// of other PUSH instructions are not mistaken for opcodes.
+pub fn get_function_sighashes(bytecode: &[u8]) -> Vec<String> {
+    let mut sighashes = vec![];
+    let mut pc = 0;
+
+    while pc < bytecode.len() {
+        let opcode = bytecode[pc];
+        pc += 1;
+
+        if (PUSH1..=PUSH32).contains(&opcode) {
+            let push_size = (opcode - PUSH1 + 1) as usize;
+            if opcode == PUSH4 && pc + push_size <= bytecode.len() {
+                let sighash = format!("0x{}", hex::encode(&bytecode[pc..pc + push_size]));
+                if sighash != "0xffffffff" {
+                    sighashes.push(sighash);
+                }
+            }
+            pc += push_size;
+        }
+    }
+
+    sighashes.sort();
+    sighashes.dedup();
+    sighashes
+}
+
+pub fn is_erc20_contract(function_sighashes: &[String]) -> bool {
+    implements_all(function_sighashes, &ERC20_SIGHASHES)
+}
+
+pub fn is_erc721_contract(function_sighashes: &[String]) -> bool {
+    implements_all(function_sighashes, &ERC721_SIGHASHES)
+        && ERC721_TRANSFER_SIGHASHES
+            .iter()
+            .any(|sighash| implements(function_sighashes, sighash))
+}
+
+fn implements(function_sighashes: &[String], sighash: &str) -> bool {
+    function_sighashes.iter().any(|s| s == sighash)
+}
+
+fn implements_all(function_sighashes: &[String], sighashes: &[&str]) -> bool {
+    sighashes
+        .iter()
+        .all(|sighash| implements(function_sighashes, sighash))
+}
+
+/// The compiler metadata that Solidity and Vyper append to runtime bytecode.
+pub struct CompilerMetadata {
+    pub compiler: Option<String>,
+    pub compiler_version: Option<String>,
+    /// `ipfs`, `bzzr0` or `bzzr1`
+    pub metadata_hash_type: Option<String>,
+    /// A base58 CID for IPFS and hex for Swarm
+    pub metadata_hash: Option<String>,
+    pub experimental: bool,
+}
+
+/// Parses the CBOR trailer at the end of runtime bytecode, whose length is given by the last
+/// two bytes.
+///
+/// Solidity appends a map with the metadata hash (`ipfs`, or `bzzr0`/`bzzr1` before 0.6), the
+/// `solc` version from 0.5.9 on and `experimental` when experimental features are enabled.
+/// Solidity versions without a `solc` entry are recognised by the metadata hash. Vyper appends
+/// `{"vyper": [major, minor, patch]}` from 0.3.4 on, wrapped in an array of section sizes from
+/// 0.3.10 on, whose length also counts the two length bytes. Returns `None` when the bytecode
+/// does not end with such a trailer.
+pub fn parse_compiler_metadata(bytecode: &[u8]) -> Option<CompilerMetadata> {
+    let (rest, length) = bytecode.split_at(bytecode.len().checked_sub(2)?);
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    let entries = read_metadata_map(rest, length).or_else(|| read_metadata_map(rest, length.checked_sub(2)?))?;
+
+    let mut metadata = CompilerMetadata {
+        compiler: None,
+        compiler_version: None,
+        metadata_hash_type: None,
+        metadata_hash: None,
+        experimental: false,
+    };
+    for (key, value) in entries {
+        let CborValue::Text(key) = key else {
+            return None;
+        };
+        match (key.as_str(), value) {
+            ("solc" | "vyper", version) => {
+                metadata.compiler = Some(key.clone());
+                metadata.compiler_version = Some(match version {
+                    CborValue::Bytes(parts) => parts.iter().map(|part| part.to_string()).collect::<Vec<_>>().join("."),
+                    CborValue::Array(parts) => parts
+                        .iter()
+                        .map(|part| match part {
+                            CborValue::Uint(part) => Some(part.to_string()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?
+                        .join("."),
+                    CborValue::Text(version) => version,
+                    _ => return None,
+                });
+            }
+            ("ipfs", CborValue::Bytes(hash)) => {
+                metadata.metadata_hash_type = Some(key.clone());
+                metadata.metadata_hash = Some(base58_encode(&hash));
+            }
+            ("bzzr0" | "bzzr1", CborValue::Bytes(hash)) => {
+                metadata.metadata_hash_type = Some(key.clone());
+                metadata.metadata_hash = Some(format!("0x{}", hex::encode(hash)));
+            }
+            ("experimental", CborValue::Bool(experimental)) => metadata.experimental = experimental,
+            _ => {}
+        }
+    }
+
+    if metadata.compiler.is_none() {
+        // Only Solidity emits a metadata hash, and before 0.5.9 nothing else
+        metadata.metadata_hash.as_ref()?;
+        metadata.compiler = Some("solc".to_string());
+    }
+
+    Some(metadata)
+}
+
+/// Reads the CBOR map in the last `length` bytes of `code`, which may be the last item of an
+/// array.
+fn read_metadata_map(code: &[u8], length: usize) -> Option<Vec<(CborValue, CborValue)>> {
+    let trailer = &code[code.len().checked_sub(length)?..];
+
+    let mut position = 0;
+    let value = read_cbor(trailer, &mut position, 0)?;
+    if position != trailer.len() {
+        return None;
+    }
+
+    match value {
+        CborValue::Map(entries) => Some(entries),
+        CborValue::Array(mut items) => match items.pop()? {
+            CborValue::Map(entries) => Some(entries),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The subset of CBOR that compilers emit in the metadata trailer.
+#[derive(Debug, PartialEq)]
+enum CborValue {
+    Uint(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CborValue>),
+    Map(Vec<(CborValue, CborValue)>),
+    Bool(bool),
+}
+
+/// Nesting deeper than the compilers produce means the bytes are not a metadata trailer.
+const MAX_CBOR_DEPTH: usize = 4;
+
+fn read_cbor(data: &[u8], position: &mut usize, depth: usize) -> Option<CborValue> {
+    if depth > MAX_CBOR_DEPTH {
+        return None;
+    }
+
+    let initial = *data.get(*position)?;
+    *position += 1;
+    let major_type = initial >> 5;
+    let additional = initial & 0x1f;
+
+    if major_type == 7 {
+        return match additional {
+            20 => Some(CborValue::Bool(false)),
+            21 => Some(CborValue::Bool(true)),
+            _ => None,
+        };
+    }
+
+    let argument = match additional {
+        0..=23 => additional as u64,
+        24..=27 => {
+            let size = 1 << (additional - 24);
+            let bytes = data.get(*position..*position + size)?;
+            *position += size;
+            bytes.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64)
+        }
+        _ => return None,
+    };
+
+    match major_type {
+        0 => Some(CborValue::Uint(argument)),
+        2 | 3 => {
+            let end = position.checked_add(usize::try_from(argument).ok()?)?;
+            let bytes = data.get(*position..end)?.to_vec();
+            *position = end;
+            if major_type == 2 {
+                Some(CborValue::Bytes(bytes))
+            } else {
+                Some(CborValue::Text(String::from_utf8(bytes).ok()?))
+            }
+        }
+        4 => {
+            let mut items = vec![];
+            for _ in 0..argument {
+                items.push(read_cbor(data, position, depth + 1)?);
+            }
+            Some(CborValue::Array(items))
+        }
+        5 => {
+            let mut entries = vec![];
+            for _ in 0..argument {
+                let key = read_cbor(data, position, depth + 1)?;
+                let value = read_cbor(data, position, depth + 1)?;
+                entries.push((key, value));
+            }
+            Some(CborValue::Map(entries))
+        }
+        _ => None,
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Bitcoin-style base58, the encoding of IPFS CIDv0 such as `Qm...`.
+fn base58_encode(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = vec![];
+    for byte in bytes {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat_n(BASE58_ALPHABET[0], leading_zeros)
+        .chain(digits.iter().rev().map(|digit| BASE58_ALPHABET[*digit as usize]))
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytecode(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    fn cbor(hex: &str) -> Option<CborValue> {
+        let data = hex::decode(hex).unwrap();
+        let mut position = 0;
+        let value = read_cbor(&data, &mut position, 0)?;
+        (position == data.len()).then_some(value)
+    }
+
+    #[test]
+    fn reads_cbor_values() {
+        assert_eq!(cbor("17"), Some(CborValue::Uint(23)));
+        assert_eq!(cbor("1818"), Some(CborValue::Uint(24)));
+        assert_eq!(cbor("190128"), Some(CborValue::Uint(296)));
+        assert_eq!(cbor("43000813"), Some(CborValue::Bytes(vec![0, 8, 19])));
+        assert_eq!(cbor("64736f6c63"), Some(CborValue::Text("solc".to_string())));
+        assert_eq!(cbor("f5"), Some(CborValue::Bool(true)));
+        assert_eq!(
+            cbor("8300030a"),
+            Some(CborValue::Array(vec![CborValue::Uint(0), CborValue::Uint(3), CborValue::Uint(10)]))
+        );
+        assert_eq!(
+            cbor("a1657679706572f4"),
+            Some(CborValue::Map(vec![(CborValue::Text("vyper".to_string()), CborValue::Bool(false))]))
+        );
+    }
+
+    #[test]
+    fn rejects_cbor_that_compilers_do_not_emit() {
+        // Truncated byte string
+        assert_eq!(cbor("5822122011"), None);
+        // Negative integer and null
+        assert_eq!(cbor("20"), None);
+        assert_eq!(cbor("f6"), None);
+        // Arrays nested deeper than any trailer
+        assert_eq!(cbor("8181818181818100"), None);
+    }
+
+    #[test]
+    fn encodes_base58() {
+        assert_eq!(base58_encode(&[]), "");
+        assert_eq!(base58_encode(&[0]), "1");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(
+            base58_encode(&bytecode("00010966776006953d5567439e5e39f86a0d273beed61967f6")),
+            "16UwLL9Risc3QfPqBUvKofHmBQ7wMtjvM"
+        );
+    }
+
+    #[test]
+    fn parses_solc_ipfs_metadata() {
+        let metadata = parse_compiler_metadata(&bytecode(
+            "6080604052600080fdfe\
+             a26469706673582212201111111111111111111111111111111111111111111111111111111111111111\
+             64736f6c63430008130033",
+        ))
+        .unwrap();
+
+        assert_eq!(metadata.compiler.as_deref(), Some("solc"));
+        assert_eq!(metadata.compiler_version.as_deref(), Some("0.8.19"));
+        assert_eq!(metadata.metadata_hash_type.as_deref(), Some("ipfs"));
+        assert_eq!(
+            metadata.metadata_hash.as_deref(),
+            Some("QmPVGjYFugq4XUyBfoTHG6c3qxfBS26jEdaFM1gdAVuMZ2")
+        );
+        assert!(!metadata.experimental);
+    }
+
+    #[test]
+    fn parses_solc_swarm_metadata_without_a_version() {
+        // The end of the WETH9 runtime, compiled with solc 0.4.19
+        let metadata = parse_compiler_metadata(&bytecode(
+            "00a165627a7a72305820deb4c2ccab3c2fdca32ab3f46728389c2fe2c165d5fafa07661e4e004f6c344a0029",
+        ))
+        .unwrap();
+
+        assert_eq!(metadata.compiler.as_deref(), Some("solc"));
+        assert_eq!(metadata.compiler_version, None);
+        assert_eq!(metadata.metadata_hash_type.as_deref(), Some("bzzr0"));
+        assert_eq!(
+            metadata.metadata_hash.as_deref(),
+            Some("0xdeb4c2ccab3c2fdca32ab3f46728389c2fe2c165d5fafa07661e4e004f6c344a")
+        );
+    }
+
+    #[test]
+    fn parses_the_solc_experimental_flag() {
+        let metadata = parse_compiler_metadata(&bytecode(
+            "fea364697066735822122022222222222222222222222222222222222222222222222222222222222222226c\
+             6578706572696d656e74616cf564736f6c63430005100041",
+        ))
+        .unwrap();
+
+        assert_eq!(metadata.compiler_version.as_deref(), Some("0.5.16"));
+        assert_eq!(
+            metadata.metadata_hash.as_deref(),
+            Some("QmQdtkyNprt8aLLivRsMoiYvxvot1SYoua5BgAg5vgc233")
+        );
+        assert!(metadata.experimental);
+    }
+
+    #[test]
+    fn parses_vyper_metadata_before_0_3_10() {
+        let metadata = parse_compiler_metadata(&bytecode("5f5ffda165767970657283000309000b")).unwrap();
+
+        assert_eq!(metadata.compiler.as_deref(), Some("vyper"));
+        assert_eq!(metadata.compiler_version.as_deref(), Some("0.3.9"));
+        assert_eq!(metadata.metadata_hash, None);
+    }
+
+    #[test]
+    fn parses_vyper_metadata_whose_length_counts_itself() {
+        // Vyper 0.3.10 writes [runtime size, data section sizes, immutables size, {"vyper": version}]
+        // and adds the two length bytes to the length
+        let metadata = parse_compiler_metadata(&bytecode("5f5ffd841901288000a16576797065728300030a0013")).unwrap();
+        assert_eq!(metadata.compiler.as_deref(), Some("vyper"));
+        assert_eq!(metadata.compiler_version.as_deref(), Some("0.3.10"));
+
+        // Vyper 0.4.1 puts an integrity hash in front
+        let metadata = parse_compiler_metadata(&bytecode(
+            "5f5ffd855820abababababababababababababababababababababababababababababababab\
+             1901288000a165767970657283000401\
+             0035",
+        ))
+        .unwrap();
+        assert_eq!(metadata.compiler_version.as_deref(), Some("0.4.1"));
+    }
+
+    #[test]
+    fn ignores_bytecode_without_metadata() {
+        // An EIP-1167 minimal proxy
+        assert!(parse_compiler_metadata(&bytecode(
+            "363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3"
+        ))
+        .is_none());
+        assert!(parse_compiler_metadata(&[0x00]).is_none());
+        assert!(parse_compiler_metadata(&[]).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A string-keyed cache that is persisted as a JSON object between runs.
+///
+/// Without a path the cache only lives in memory for the duration of the job.
+#[derive(Clone)]
+pub struct DiskCache<V> {
+    path: Option<PathBuf>,
+    entries: Arc<Mutex<HashMap<String, V>>>,
+}
+
+impl<V> DiskCache<V>
+where
+    V: Serialize + DeserializeOwned + Clone,
+{
+    pub fn load(path: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let entries = match &path {
+            Some(path) if path.exists() => {
+                let reader = BufReader::new(File::open(path)?);
+                serde_json::from_reader(reader)?
+            }
+            _ => HashMap::new(),
+        };
+
+        Ok(DiskCache {
+            path,
+            entries: Arc::new(Mutex::new(entries)),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        self.entries.lock().unwrap().insert(key, value);
+    }
+
+    /// Writes the cache to a temporary file first so an interrupted run cannot corrupt it.
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let entries = self.entries.lock().unwrap();
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            serde_json::to_writer(&mut writer, &*entries)?;
+            writer.flush()?;
+        }
+        std::fs::rename(tmp_path, path)
+    }
+}
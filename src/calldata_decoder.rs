@@ -0,0 +1,298 @@
+use crate::abi::{read_abi_dir, token_to_json};
+use anyhow::{Context, Result};
+use ethers::abi::param_type::Reader;
+use ethers::abi::{decode, encode, Function, ParamType};
+use ethers::utils::keccak256;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A function that a selector may stand for, with the parameter names when an ABI defines it.
+struct FunctionCandidate {
+    name: String,
+    signature: String,
+    params: Vec<(String, ParamType)>,
+}
+
+/// The function a transaction input calls, as written to the transaction columns.
+pub struct DecodedCall {
+    /// The canonical signature, or every candidate signature separated by `|` when the selector
+    /// is ambiguous
+    pub method_signature: String,
+    /// The name of the function the arguments were decoded with, or every candidate name
+    /// separated by `|` when none was
+    pub method_name: String,
+    /// The arguments as a JSON object keyed by parameter name; `None` unless exactly one
+    /// candidate fits the calldata
+    pub decoded_args: Option<String>,
+}
+
+/// Resolves 4-byte function selectors with the functions of a directory of ABI files and a
+/// 4byte-style signature database.
+///
+/// A signature found in both keeps the ABI definition, which also names the parameters.
+pub struct CalldataDecoder {
+    functions: HashMap<[u8; 4], Vec<FunctionCandidate>>,
+}
+
+impl CalldataDecoder {
+    /// `signatures_db` is a text file with one signature such as `transfer(address,uint256)` per
+    /// line, optionally preceded by its selector and a tab. Lines that do not parse are skipped.
+    pub fn load(abi_dir: Option<&Path>, signatures_db: Option<&Path>) -> Result<Self> {
+        let mut functions: HashMap<[u8; 4], Vec<FunctionCandidate>> = HashMap::new();
+        if let Some(abi_dir) = abi_dir {
+            for abi in read_abi_dir(abi_dir)? {
+                for item in &abi.items {
+                    if item.get("type").and_then(|kind| kind.as_str()) != Some("function") {
+                        continue;
+                    }
+                    let function: Function = serde_json::from_value(item.clone())
+                        .with_context(|| format!("Invalid function in {}.json", abi.name))?;
+                    let params = function
+                        .inputs
+                        .into_iter()
+                        .map(|input| (input.name, input.kind))
+                        .collect();
+                    insert_candidate(&mut functions, function.name, params);
+                }
+            }
+        }
+
+        if let Some(signatures_db) = signatures_db {
+            let content = std::fs::read_to_string(signatures_db)
+                .with_context(|| format!("Failed to read signature database {}", signatures_db.display()))?;
+            for line in content.lines() {
+                let signature = line.rsplit('\t').next().unwrap_or_default().trim();
+                if let Some((name, kinds)) = parse_signature(signature) {
+                    let params = kinds.into_iter().map(|kind| (String::new(), kind)).collect();
+                    insert_candidate(&mut functions, name, params);
+                }
+            }
+        }
+
+        Ok(CalldataDecoder { functions })
+    }
+
+    pub fn selectors_count(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Resolves the selector of a transaction input and decodes its arguments.
+    ///
+    /// Every candidate of the selector is listed in `method_signature`. When exactly one of them
+    /// has parameters that fit the calldata, its arguments are decoded; otherwise the call has no
+    /// arguments. Returns `None` for inputs shorter than a selector and for unknown selectors.
+    pub fn decode(&self, input: &str) -> Option<DecodedCall> {
+        let input = hex::decode(input.trim_start_matches("0x")).ok()?;
+        if input.len() < 4 {
+            return None;
+        }
+        let (selector, data) = input.split_at(4);
+        let selector: [u8; 4] = selector.try_into().ok()?;
+
+        let candidates = self.functions.get(&selector)?;
+        let method_signature = candidates
+            .iter()
+            .map(|candidate| candidate.signature.as_str())
+            .collect::<Vec<_>>()
+            .join("|");
+
+        let mut decoded = candidates
+            .iter()
+            .filter_map(|candidate| {
+                let kinds = candidate.params.iter().map(|(_, kind)| kind.clone()).collect::<Vec<_>>();
+                let tokens = decode(&kinds, data).ok()?;
+                // The decoder tolerates padding and offsets that no encoder produces, which is
+                // how unrelated signatures would otherwise fit. Trailing bytes are allowed.
+                data.starts_with(&encode(&tokens)).then_some((candidate, tokens))
+            })
+            .collect::<Vec<_>>();
+
+        if decoded.len() == 1 {
+            let (candidate, tokens) = decoded.remove(0);
+            // Written by hand because `serde_json::Map` would sort the parameters by name
+            let args = candidate
+                .params
+                .iter()
+                .zip(&tokens)
+                .enumerate()
+                .map(|(position, ((name, _), token))| {
+                    let name = if name.is_empty() { format!("arg{}", position) } else { name.clone() };
+                    format!("{}:{}", serde_json::Value::String(name), token_to_json(token))
+                })
+                .collect::<Vec<_>>();
+
+            return Some(DecodedCall {
+                method_signature,
+                method_name: candidate.name.clone(),
+                decoded_args: Some(format!("{{{}}}", args.join(","))),
+            });
+        }
+
+        let mut names: Vec<&str> = vec![];
+        for candidate in candidates {
+            if !names.contains(&candidate.name.as_str()) {
+                names.push(&candidate.name);
+            }
+        }
+
+        Some(DecodedCall {
+            method_signature,
+            method_name: names.join("|"),
+            decoded_args: None,
+        })
+    }
+}
+
+/// Adds a function under its selector unless a function with the same signature is known.
+fn insert_candidate(
+    functions: &mut HashMap<[u8; 4], Vec<FunctionCandidate>>,
+    name: String,
+    params: Vec<(String, ParamType)>,
+) {
+    let kinds = params.iter().map(|(_, kind)| kind.to_string()).collect::<Vec<_>>();
+    let signature = format!("{}({})", name, kinds.join(","));
+    let hash = keccak256(signature.as_bytes());
+    let selector = [hash[0], hash[1], hash[2], hash[3]];
+
+    let candidates = functions.entry(selector).or_default();
+    if candidates.iter().all(|candidate| candidate.signature != signature) {
+        candidates.push(FunctionCandidate { name, signature, params });
+    }
+}
+
+/// Splits a canonical text signature such as `swap((address,uint256),bytes)` into its name and
+/// parameter types.
+fn parse_signature(signature: &str) -> Option<(String, Vec<ParamType>)> {
+    let (name, params) = signature.split_once('(')?;
+    let valid_name = name
+        .chars()
+        .enumerate()
+        .all(|(position, c)| c == '_' || c == '$' || c.is_ascii_alphabetic() || (position > 0 && c.is_ascii_digit()));
+    if name.is_empty() || !valid_name || !params.ends_with(')') {
+        return None;
+    }
+
+    if params == ")" {
+        return Some((name.to_string(), vec![]));
+    }
+    let ParamType::Tuple(kinds) = Reader::read(&format!("({}", params)).ok()? else {
+        return None;
+    };
+    // The reader takes unknown type names for enums and reads them as `uint8`
+    let canonical = kinds.iter().map(|kind| kind.to_string()).collect::<Vec<_>>().join(",");
+    (format!("{})", canonical) == params).then(|| (name.to_string(), kinds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ERC20_ABI: &str = r#"[{"type":"function","name":"transfer","stateMutability":"nonpayable",
+        "inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],
+        "outputs":[{"name":"","type":"bool"}]}]"#;
+
+    /// `transfer(address,uint256)` and the two other signatures that share its selector
+    const SIGNATURES_DB: &str = "0xa9059cbb\ttransfer(address,uint256)\n\
+        0xa9059cbb\tmany_msg_babbage(bytes1)\n\
+        0xa9059cbb\ttransfer(bytes4[9],bytes5[6],int48[11])\n\
+        not a signature\n";
+
+    /// 1,500 USDC sent to 0x21a31ee1afc51d94c2efccaa2092ad1028285549
+    const USDC_TRANSFER_INPUT: &str = "0xa9059cbb\
+        00000000000000000000000021a31ee1afc51d94c2efccaa2092ad1028285549\
+        0000000000000000000000000000000000000000000000000000000059682f00";
+
+    /// A directory holding `signatures.tsv`, deleted when dropped.
+    fn signatures_db() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("signatures.tsv"), SIGNATURES_DB).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_text_signatures() {
+        assert_eq!(
+            parse_signature("transfer(address,uint256)"),
+            Some(("transfer".to_string(), vec![ParamType::Address, ParamType::Uint(256)]))
+        );
+        assert_eq!(
+            parse_signature("swap((address,uint256)[],bytes)"),
+            Some((
+                "swap".to_string(),
+                vec![
+                    ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(256)]))),
+                    ParamType::Bytes,
+                ]
+            ))
+        );
+        assert_eq!(parse_signature("totalSupply()"), Some(("totalSupply".to_string(), vec![])));
+        assert_eq!(parse_signature("$_init2(bool)"), Some(("$_init2".to_string(), vec![ParamType::Bool])));
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        assert_eq!(parse_signature("1transfer(address)"), None);
+        assert_eq!(parse_signature("transfer(address"), None);
+        assert_eq!(parse_signature("transfer(addr)"), None);
+        assert_eq!(parse_signature("(address)"), None);
+        assert_eq!(parse_signature("transfer"), None);
+    }
+
+    #[test]
+    fn decodes_named_arguments_from_an_abi() {
+        let abi_dir = TempDir::new().unwrap();
+        std::fs::write(abi_dir.path().join("erc20.json"), ERC20_ABI).unwrap();
+        let decoder = CalldataDecoder::load(Some(abi_dir.path()), None).unwrap();
+
+        let call = decoder.decode(USDC_TRANSFER_INPUT).unwrap();
+        assert_eq!(call.method_signature, "transfer(address,uint256)");
+        assert_eq!(call.method_name, "transfer");
+        assert_eq!(
+            call.decoded_args.as_deref(),
+            Some(r#"{"to":"0x21a31ee1afc51d94c2efccaa2092ad1028285549","value":"1500000000"}"#)
+        );
+    }
+
+    #[test]
+    fn lists_every_candidate_and_decodes_the_one_that_fits() {
+        let dir = signatures_db();
+        let decoder = CalldataDecoder::load(None, Some(&dir.path().join("signatures.tsv"))).unwrap();
+        assert_eq!(decoder.selectors_count(), 1);
+
+        let call = decoder.decode(USDC_TRANSFER_INPUT).unwrap();
+        assert_eq!(
+            call.method_signature,
+            "transfer(address,uint256)|many_msg_babbage(bytes1)|transfer(bytes4[9],bytes5[6],int48[11])"
+        );
+        assert_eq!(call.method_name, "transfer");
+        assert_eq!(
+            call.decoded_args.as_deref(),
+            Some(r#"{"arg0":"0x21a31ee1afc51d94c2efccaa2092ad1028285549","arg1":"1500000000"}"#)
+        );
+    }
+
+    #[test]
+    fn leaves_arguments_empty_when_no_candidate_fits() {
+        let dir = signatures_db();
+        let decoder = CalldataDecoder::load(None, Some(&dir.path().join("signatures.tsv"))).unwrap();
+
+        let call = decoder.decode("0xa9059cbb").unwrap();
+        assert_eq!(
+            call.method_signature,
+            "transfer(address,uint256)|many_msg_babbage(bytes1)|transfer(bytes4[9],bytes5[6],int48[11])"
+        );
+        assert_eq!(call.method_name, "transfer|many_msg_babbage");
+        assert_eq!(call.decoded_args, None);
+    }
+
+    #[test]
+    fn ignores_unknown_selectors_and_short_inputs() {
+        let dir = signatures_db();
+        let decoder = CalldataDecoder::load(None, Some(&dir.path().join("signatures.tsv"))).unwrap();
+
+        assert!(decoder.decode("0x095ea7b3").is_none());
+        assert!(decoder.decode("0xa9059c").is_none());
+        assert!(decoder.decode("0x").is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The forks that change what a block pays its producer, and the ETH allocated at genesis. A
+/// missing fork is not activated.
+///
+/// Loaded from a JSON file in this tool's own format, for example
+/// `{"byzantiumBlock": 4370000, "constantinopleBlock": 7280000, "mergeBlock": 15537394}`.
+/// `mergeBlock` is the first proof-of-stake block and must be given, as `null` for a chain that
+/// has not merged. `genesisSupply` is a decimal string in wei. Other keys are ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainConfig {
+    #[serde(default)]
+    pub byzantium_block: Option<u64>,
+    #[serde(default)]
+    pub constantinople_block: Option<u64>,
+    // Without a default, serde requires the key even though the value may be null
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub merge_block: Option<u64>,
+    #[serde(default)]
+    pub genesis_supply: Option<String>,
+}
+
+impl ChainConfig {
+    pub fn mainnet() -> Self {
+        ChainConfig {
+            byzantium_block: Some(4_370_000),
+            constantinople_block: Some(7_280_000),
+            merge_block: Some(15_537_394),
+            genesis_supply: Some("72009990499480000000000000".to_string()),
+        }
+    }
+
+    /// Reads a chain config file, or returns the mainnet config without one.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::mainnet());
+        };
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read chain config {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid chain config {}", path.display()))
+    }
+
+    pub fn is_byzantium(&self, block_number: u64) -> bool {
+        is_activated(self.byzantium_block, block_number)
+    }
+
+    pub fn is_constantinople(&self, block_number: u64) -> bool {
+        is_activated(self.constantinople_block, block_number)
+    }
+
+    pub fn is_merged(&self, block_number: u64) -> bool {
+        is_activated(self.merge_block, block_number)
+    }
+}
+
+fn is_activated(fork_block: Option<u64>, block_number: u64) -> bool {
+    fork_block.is_some_and(|fork_block| block_number >= fork_block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> serde_json::Result<ChainConfig> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn reads_chain_configs() {
+        let config = parse(
+            r#"{"chainId": 1, "byzantiumBlock": 4370000, "constantinopleBlock": 7280000,
+                "petersburgBlock": 7280000, "mergeBlock": 15537394}"#,
+        )
+        .unwrap();
+
+        assert!(!config.is_byzantium(4_369_999));
+        assert!(config.is_constantinople(7_280_000));
+        assert!(!config.is_merged(15_537_393));
+        assert!(config.is_merged(15_537_394));
+    }
+
+    #[test]
+    fn requires_the_merge_block() {
+        assert!(parse(r#"{"byzantiumBlock": 0, "mergeNetsplitBlock": 1735371}"#).is_err());
+
+        let config = parse(r#"{"byzantiumBlock": 0, "mergeBlock": null}"#).unwrap();
+        assert!(!config.is_merged(u64::MAX));
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Block {
@@ -29,8 +29,18 @@ pub struct Block {
     pub blob_gas_used: Option<u64>,
     #[serde(default)]
     pub excess_blob_gas: Option<u64>,
+    #[serde(default)]
+    pub parent_beacon_block_root: Option<String>,
+    #[serde(default)]
+    pub requests_hash: Option<String>,
+    #[serde(default)]
+    pub mix_hash: Option<String>,
+    #[serde(default)]
+    pub withdrawals_count: Option<u64>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub transactions: Vec<Transaction>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub withdrawals: Vec<Withdrawal>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,13 +58,373 @@ pub struct Transaction {
     pub input: String,
     pub block_timestamp: u64,
     #[serde(default)]
-    pub max_fee_per_gas: Option<u64>,
+    pub max_fee_per_gas: Option<String>,
     #[serde(default)]
-    pub max_priority_fee_per_gas: Option<u64>,
+    pub max_priority_fee_per_gas: Option<String>,
     #[serde(default)]
     pub transaction_type: Option<u64>,
     #[serde(default)]
-    pub max_fee_per_blob_gas: Option<u64>,
+    pub max_fee_per_blob_gas: Option<String>,
+    #[serde(default)]
+    pub blob_versioned_hashes: Vec<String>,
+    #[serde(default)]
+    pub receipt_status: Option<u64>,
+    #[serde(default)]
+    pub receipt_gas_used: Option<u64>,
+    #[serde(default)]
+    pub receipt_cumulative_gas_used: Option<u64>,
+    #[serde(default)]
+    pub receipt_effective_gas_price: Option<String>,
+    #[serde(default)]
+    pub receipt_contract_address: Option<String>,
+    #[serde(default)]
+    pub receipt_blob_gas_used: Option<u64>,
+    #[serde(default)]
+    pub receipt_blob_gas_price: Option<u64>,
+    #[serde(default)]
+    pub receipt_logs_count: Option<u64>,
+    /// The EIP-2930 access list; `None` for legacy transactions, which cannot carry one.
+    #[serde(default)]
+    pub access_list: Option<Vec<AccessListItem>>,
+    /// The EIP-7702 authorization list of set-code (type 4) transactions.
+    #[serde(default)]
+    pub authorization_list: Option<Vec<Authorization>>,
+    #[serde(default)]
+    pub v: Option<u64>,
+    #[serde(default)]
+    pub r: Option<String>,
+    #[serde(default)]
+    pub s: Option<String>,
+    #[serde(default)]
+    pub y_parity: Option<u64>,
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// The sender recovered from the signature; only set when signatures are verified.
+    #[serde(default)]
+    pub recovered_from_address: Option<String>,
+    /// Whether the recovered sender differs from the `from` reported by the node, or the
+    /// signature does not recover at all.
+    #[serde(default)]
+    pub from_address_mismatch: Option<bool>,
+    /// The function the input calls, resolved from its selector; only set when calldata is
+    /// decoded. Ambiguous selectors list every candidate separated by `|`.
+    #[serde(default)]
+    pub method_signature: Option<String>,
+    #[serde(default)]
+    pub method_name: Option<String>,
+    /// The arguments as a JSON object keyed by parameter name.
+    #[serde(default)]
+    pub decoded_args: Option<String>,
+    /// The price per gas actually paid, in wei.
+    #[serde(default)]
+    pub effective_gas_price: Option<String>,
+    /// Fees in wei, which need the gas used from the receipt.
+    #[serde(default)]
+    pub burnt_fee: Option<String>,
+    #[serde(default)]
+    pub priority_fee_paid: Option<String>,
+    #[serde(default)]
+    pub blob_fee: Option<String>,
+    #[serde(default)]
+    pub total_fee: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessListItem {
+    pub address: String,
+    pub storage_keys: Vec<String>,
+}
+
+/// An EIP-7702 authorization tuple. `authority` is the account recovered from the signature,
+/// which delegates its code to `address`; it is empty when the signature is invalid.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Authorization {
+    pub chain_id: String,
+    pub address: String,
+    pub nonce: u64,
+    pub y_parity: u64,
+    pub r: String,
+    pub s: String,
+    pub authority: Option<String>,
+}
+
+/// An authorization together with the transaction that carried it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionAuthorization {
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub transaction_index: Option<u64>,
+    pub authorization_index: u64,
+    #[serde(flatten)]
+    pub authorization: Authorization,
+}
+
+/// One (address, storage key) pair of a transaction access list. Addresses listed without any
+/// storage key get a single entry without one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionAccessListEntry {
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub transaction_index: Option<u64>,
+    pub address: String,
+    pub storage_key: Option<String>,
+}
+
+/// An uncle (ommer) header together with its position in the block that included it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Uncle {
+    #[serde(flatten)]
+    pub block: Block,
+    pub including_block_number: u64,
+    pub including_block_hash: String,
+    pub uncle_index: u64,
+}
+
+/// The rewards and fees of a block, in wei.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockReward {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub miner: String,
+    pub static_reward: String,
+    pub uncle_inclusion_reward: String,
+    pub uncle_rewards: Vec<UncleReward>,
+    #[serde(default)]
+    pub total_priority_fees: Option<String>,
+    #[serde(default)]
+    pub burnt_fees: Option<String>,
+    /// What the block producer earned: the static and uncle inclusion rewards plus priority fees.
+    #[serde(default)]
+    pub total_reward: Option<String>,
+}
+
+/// The ETH supply after a block and the changes that led to it, in wei. `supply_delta` is signed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EthSupply {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub issuance: String,
+    pub burnt_fees: String,
+    pub withdrawals: String,
+    pub blob_fees_burnt: String,
+    pub supply_delta: String,
+    pub supply: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UncleReward {
+    pub uncle_index: u64,
+    pub miner: String,
+    pub reward: String,
+}
+
+/// An EIP-4895 beacon chain withdrawal. `amount` is in gwei, as reported by the node.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: String,
+    pub amount: u64,
+    pub block_number: u64,
+    pub block_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    pub block_hash: Option<String>,
+    pub block_number: Option<u64>,
+    pub cumulative_gas_used: u64,
+    pub gas_used: Option<u64>,
+    pub contract_address: Option<String>,
+    pub root: Option<String>,
+    pub status: Option<u64>,
+    #[serde(default)]
+    pub effective_gas_price: Option<String>,
+    #[serde(default)]
+    pub blob_gas_used: Option<u64>,
+    #[serde(default)]
+    pub blob_gas_price: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub logs: Vec<Log>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Log {
+    pub log_index: Option<u64>,
+    pub transaction_hash: Option<String>,
+    pub transaction_index: Option<u64>,
+    pub block_hash: Option<String>,
+    pub block_number: Option<u64>,
+    pub address: String,
+    pub data: String,
+    pub topics: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenTransfer {
+    pub token_address: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub value: String,
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub block_number: Option<u64>,
+    #[serde(default)]
+    pub operator: Option<String>,
+    #[serde(default)]
+    pub token_id: Option<String>,
+    #[serde(default)]
+    pub batch_index: Option<u64>,
+    pub token_standard: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Contract {
+    pub address: String,
+    pub bytecode: String,
+    pub function_sighashes: Vec<String>,
+    pub is_erc20: bool,
+    pub is_erc721: bool,
+    pub block_number: u64,
+    /// `eip1167`, `eip1967`, `eip1967_beacon` or `eip1822` when the contract is a proxy.
+    #[serde(default)]
+    pub proxy_type: Option<String>,
+    #[serde(default)]
+    pub implementation_address: Option<String>,
+    #[serde(default)]
+    pub proxy_admin_address: Option<String>,
+    #[serde(default)]
+    pub beacon_address: Option<String>,
+    /// From the CBOR metadata trailer of the bytecode: `solc` or `vyper`.
+    #[serde(default)]
+    pub compiler: Option<String>,
+    #[serde(default)]
+    pub compiler_version: Option<String>,
+    #[serde(default)]
+    pub metadata_hash_type: Option<String>,
+    #[serde(default)]
+    pub metadata_hash: Option<String>,
     #[serde(default)]
-    pub blob_versioned_hashes: Vec<String>
-}
\ No newline at end of file
+    pub experimental: Option<bool>,
+}
+
+/// An `Upgraded` or `BeaconUpgraded` event, which records a new implementation or beacon.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyUpgrade {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub log_index: u64,
+    pub address: String,
+    #[serde(default)]
+    pub implementation_address: Option<String>,
+    #[serde(default)]
+    pub beacon_address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Token {
+    pub address: String,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: Option<u64>,
+    pub total_supply: Option<String>,
+    pub block_number: u64,
+}
+
+/// Token fields that are cached between runs of `export_tokens`. `total_supply` changes over
+/// time and is kept per block number.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenMetadata {
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: Option<u64>,
+    #[serde(default)]
+    pub total_supply: BTreeMap<u64, Option<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trace {
+    pub block_number: u64,
+    pub transaction_hash: Option<String>,
+    pub transaction_index: Option<u64>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: String,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub trace_type: String,
+    pub call_type: Option<String>,
+    pub reward_type: Option<String>,
+    pub gas: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub subtraces: u64,
+    pub trace_address: Vec<u64>,
+    pub error: Option<String>,
+    pub status: u64,
+    pub trace_id: String,
+}
+
+/// Account-level changes made by a transaction. Only the fields that changed are set, so a row
+/// can carry a balance change, a nonce change, a code change or any combination of them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BalanceChange {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    pub address: String,
+    pub balance_before: Option<String>,
+    pub balance_after: Option<String>,
+    pub nonce_before: Option<u64>,
+    pub nonce_after: Option<u64>,
+    pub code_hash_before: Option<String>,
+    pub code_hash_after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageChange {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    pub address: String,
+    pub slot: String,
+    pub value_before: String,
+    pub value_after: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountState {
+    pub block_number: u64,
+    pub address: String,
+    pub balance: String,
+    pub nonce: u64,
+    pub code_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenBalance {
+    pub block_number: u64,
+    pub token_address: String,
+    pub holder_address: String,
+    pub balance: Option<String>,
+}
+
+/// An EIP-7685 execution layer request. `request_type` is 0 for deposits, 1 for withdrawal
+/// requests and 2 for consolidation requests, and decides which fields are set: `pubkey` is the
+/// deposited validator, the validator to withdraw from or the consolidation source; `amount` is in
+/// gwei. `request_index` is the position among the block's requests of the same type.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExecutionRequest {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub request_type: u64,
+    pub request_index: u64,
+    pub source_address: Option<String>,
+    pub pubkey: Option<String>,
+    pub target_pubkey: Option<String>,
+    pub withdrawal_credentials: Option<String>,
+    pub amount: Option<u64>,
+    pub signature: Option<String>,
+    pub deposit_index: Option<u64>,
+}
@@ -1,106 +1,670 @@
+use crate::mappers::block_csv_header;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use csv::Writer;
-use crate::domain::{Block, Transaction};
-use crate::mappers::{block_to_csv_row, transaction_to_csv_row};
+
+type SharedWriter = Arc<Mutex<Option<Writer<File>>>>;
+
+fn create_writer(output: Option<PathBuf>, header: &[&str]) -> Result<SharedWriter, std::io::Error> {
+    let writer = match output {
+        Some(path) => {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(header)?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    Ok(Arc::new(Mutex::new(writer)))
+}
+
+/// Opens an existing CSV file to add rows after the ones it already has.
+fn append_writer(output: PathBuf) -> Result<SharedWriter, std::io::Error> {
+    let file = std::fs::OpenOptions::new().append(true).open(output)?;
+    let writer = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+
+    Ok(Arc::new(Mutex::new(Some(writer))))
+}
+
+fn write_rows(writer: &SharedWriter, rows: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+    if let Some(writer) = writer.lock().unwrap().as_mut() {
+        for row in rows {
+            writer.write_record(&row)?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+const LOG_COLUMNS: [&str; 8] = [
+    "log_index",
+    "transaction_hash",
+    "transaction_index",
+    "block_hash",
+    "block_number",
+    "address",
+    "data",
+    "topics",
+];
 
 #[derive(Clone)]
 pub struct CsvExporter {
-    blocks_writer: Arc<Mutex<Option<Writer<File>>>>,
-    transactions_writer: Arc<Mutex<Option<Writer<File>>>>,
+    blocks_writer: SharedWriter,
+    transactions_writer: SharedWriter,
+    withdrawals_writer: SharedWriter,
+    uncles_writer: SharedWriter,
+    access_list_writer: SharedWriter,
+    authorizations_writer: SharedWriter,
+    block_rewards_writer: SharedWriter,
 }
 
 impl CsvExporter {
     pub fn new(
         blocks_output: Option<PathBuf>,
         transactions_output: Option<PathBuf>,
+        withdrawals_output: Option<PathBuf>,
+        uncles_output: Option<PathBuf>,
+        access_list_output: Option<PathBuf>,
+        authorizations_output: Option<PathBuf>,
+        block_rewards_output: Option<PathBuf>,
     ) -> Result<Self, std::io::Error> {
-        let blocks_writer = match blocks_output {
-            Some(path) => {
-                let mut writer = csv::Writer::from_path(path)?;
-                writer.write_record(&[
-                    "number",
-                    "hash",
-                    "parent_hash",
-                    "nonce",
-                    "sha3_uncles",
-                    "logs_bloom",
-                    "transactions_root",
-                    "state_root",
-                    "receipts_root",
-                    "miner",
-                    "difficulty",
-                    "total_difficulty",
-                    "size",
-                    "extra_data",
-                    "gas_limit",
-                    "gas_used",
-                    "timestamp",
-                    "transaction_count",
-                    "base_fee_per_gas",
-                    "withdrawals_root",
-                    "blob_gas_used",
-                    "excess_blob_gas"
-                ])?;
-                Some(writer)
-            }
-            None => None,
-        };
+        let blocks_writer = create_writer(blocks_output, &block_csv_header())?;
 
-        let transactions_writer = match transactions_output {
-            Some(path) => {
-                let mut writer = csv::Writer::from_path(path)?;
-                writer.write_record(&[
-                    "hash",
-                    "nonce",
-                    "block_hash",
-                    "block_number",
-                    "transaction_index",
-                    "from_address",
-                    "to_address",
-                    "value",
-                    "gas",
-                    "gas_price",
-                    "input",
-                    "block_timestamp",
-                    "max_fee_per_gas",
-                    "max_priority_fee_per_gas",
-                    "transaction_type",
-                    "max_fee_per_blob_gas",
-                    "blob_versioned_hashes"
-                ])?;
-                Some(writer)
-            }
-            None => None,
-        };
+        let transactions_writer = create_writer(
+            transactions_output,
+            &[
+                "hash",
+                "nonce",
+                "block_hash",
+                "block_number",
+                "transaction_index",
+                "from_address",
+                "to_address",
+                "value",
+                "gas",
+                "gas_price",
+                "input",
+                "block_timestamp",
+                "max_fee_per_gas",
+                "max_priority_fee_per_gas",
+                "transaction_type",
+                "max_fee_per_blob_gas",
+                "blob_versioned_hashes",
+                "receipt_status",
+                "receipt_gas_used",
+                "receipt_cumulative_gas_used",
+                "receipt_effective_gas_price",
+                "receipt_contract_address",
+                "receipt_blob_gas_used",
+                "receipt_blob_gas_price",
+                "receipt_logs_count",
+                "access_list",
+                "authorization_list",
+                "v",
+                "r",
+                "s",
+                "y_parity",
+                "chain_id",
+                "recovered_from_address",
+                "from_address_mismatch",
+                "method_signature",
+                "method_name",
+                "decoded_args",
+                "effective_gas_price",
+                "burnt_fee",
+                "priority_fee_paid",
+                "blob_fee",
+                "total_fee"
+            ],
+        )?;
+
+        let withdrawals_writer = create_writer(
+            withdrawals_output,
+            &[
+                "index",
+                "validator_index",
+                "address",
+                "amount",
+                "block_number",
+                "block_hash"
+            ],
+        )?;
+
+        let uncle_columns = [
+            &block_csv_header()[..],
+            &["including_block_number", "including_block_hash", "uncle_index"],
+        ]
+        .concat();
+        let uncles_writer = create_writer(uncles_output, &uncle_columns)?;
+
+        let access_list_writer = create_writer(
+            access_list_output,
+            &[
+                "transaction_hash",
+                "block_number",
+                "transaction_index",
+                "address",
+                "storage_key"
+            ],
+        )?;
+
+        let authorizations_writer = create_writer(
+            authorizations_output,
+            &[
+                "transaction_hash",
+                "block_number",
+                "transaction_index",
+                "authorization_index",
+                "chain_id",
+                "address",
+                "nonce",
+                "y_parity",
+                "r",
+                "s",
+                "authority"
+            ],
+        )?;
+
+        let block_rewards_writer = create_writer(
+            block_rewards_output,
+            &[
+                "block_number",
+                "block_hash",
+                "miner",
+                "static_reward",
+                "uncle_inclusion_reward",
+                "uncle_rewards",
+                "total_priority_fees",
+                "burnt_fees",
+                "total_reward"
+            ],
+        )?;
 
         Ok(CsvExporter {
-            blocks_writer: Arc::new(Mutex::new(blocks_writer)),
-            transactions_writer: Arc::new(Mutex::new(transactions_writer)),
+            blocks_writer,
+            transactions_writer,
+            withdrawals_writer,
+            uncles_writer,
+            access_list_writer,
+            authorizations_writer,
+            block_rewards_writer,
         })
     }
 
+    /// Uncles cost extra RPC calls, so jobs only fetch them when they are written somewhere.
+    pub fn exports_uncles(&self) -> bool {
+        self.uncles_writer.lock().unwrap().is_some()
+    }
+
+    pub fn export_uncles(&self, uncles: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.uncles_writer, uncles)
+    }
+
+    /// Block rewards need the uncles and the receipts of every block, so jobs only fetch them
+    /// for this output when it is written somewhere.
+    pub fn exports_block_rewards(&self) -> bool {
+        self.block_rewards_writer.lock().unwrap().is_some()
+    }
+
+    pub fn export_block_rewards(&self, block_rewards: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.block_rewards_writer, block_rewards)
+    }
+
+    pub fn export_access_list(&self, access_list: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.access_list_writer, access_list)
+    }
+
+    pub fn export_authorizations(&self, authorizations: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.authorizations_writer, authorizations)
+    }
+
     pub fn export_blocks_and_transactions(
         &self,
         blocks: Vec<Vec<String>>,
         transactions: Vec<Vec<String>>,
+        withdrawals: Vec<Vec<String>>,
     ) -> Result<(), std::io::Error> {
-        if let Some(mut writer) = self.blocks_writer.lock().unwrap().as_mut() {
-            for block in blocks {
-                writer.write_record(&block)?;
-            }
-            writer.flush()?;
-        }
+        write_rows(&self.blocks_writer, blocks)?;
+        write_rows(&self.transactions_writer, transactions)?;
+        write_rows(&self.withdrawals_writer, withdrawals)?;
 
-        if let Some(mut writer) = self.transactions_writer.lock().unwrap().as_mut() {
-            for transaction in transactions {
-                writer.write_record(&transaction)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct ReceiptsCsvExporter {
+    receipts_writer: SharedWriter,
+    logs_writer: SharedWriter,
+}
+
+impl ReceiptsCsvExporter {
+    pub fn new(
+        receipts_output: Option<PathBuf>,
+        logs_output: Option<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
+        let receipts_writer = create_writer(
+            receipts_output,
+            &[
+                "transaction_hash",
+                "transaction_index",
+                "block_hash",
+                "block_number",
+                "cumulative_gas_used",
+                "gas_used",
+                "contract_address",
+                "root",
+                "status",
+                "effective_gas_price",
+                "blob_gas_used",
+                "blob_gas_price"
+            ],
+        )?;
+
+        let logs_writer = create_writer(logs_output, &LOG_COLUMNS)?;
+
+        Ok(ReceiptsCsvExporter {
+            receipts_writer,
+            logs_writer,
+        })
+    }
+
+    pub fn export_receipts_and_logs(
+        &self,
+        receipts: Vec<Vec<String>>,
+        logs: Vec<Vec<String>>,
+    ) -> Result<(), std::io::Error> {
+        write_rows(&self.receipts_writer, receipts)?;
+        write_rows(&self.logs_writer, logs)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenTransfersCsvExporter {
+    token_transfers_writer: SharedWriter,
+}
+
+impl TokenTransfersCsvExporter {
+    pub fn new(token_transfers_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let token_transfers_writer = create_writer(
+            token_transfers_output,
+            &[
+                "token_address",
+                "from_address",
+                "to_address",
+                "value",
+                "transaction_hash",
+                "log_index",
+                "block_number",
+                "operator",
+                "token_id",
+                "batch_index",
+                "token_standard"
+            ],
+        )?;
+
+        Ok(TokenTransfersCsvExporter {
+            token_transfers_writer,
+        })
+    }
+
+    pub fn export_token_transfers(&self, token_transfers: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.token_transfers_writer, token_transfers)
+    }
+}
+
+#[derive(Clone)]
+pub struct ContractsCsvExporter {
+    contracts_writer: SharedWriter,
+    proxy_upgrades_writer: SharedWriter,
+}
+
+impl ContractsCsvExporter {
+    pub fn new(contracts_output: Option<PathBuf>, proxy_upgrades_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let contracts_writer = create_writer(
+            contracts_output,
+            &[
+                "address",
+                "bytecode",
+                "function_sighashes",
+                "is_erc20",
+                "is_erc721",
+                "block_number",
+                "proxy_type",
+                "implementation_address",
+                "proxy_admin_address",
+                "beacon_address",
+                "compiler",
+                "compiler_version",
+                "metadata_hash_type",
+                "metadata_hash",
+                "experimental"
+            ],
+        )?;
+
+        let proxy_upgrades_writer = create_writer(
+            proxy_upgrades_output,
+            &[
+                "block_number",
+                "transaction_hash",
+                "log_index",
+                "address",
+                "implementation_address",
+                "beacon_address"
+            ],
+        )?;
+
+        Ok(ContractsCsvExporter {
+            contracts_writer,
+            proxy_upgrades_writer,
+        })
+    }
+
+    pub fn export_contracts(&self, contracts: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.contracts_writer, contracts)
+    }
+
+    /// Proxy upgrades need a log query per block, so jobs only fetch them when they are written
+    /// somewhere.
+    pub fn exports_proxy_upgrades(&self) -> bool {
+        self.proxy_upgrades_writer.lock().unwrap().is_some()
+    }
+
+    pub fn export_proxy_upgrades(&self, proxy_upgrades: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.proxy_upgrades_writer, proxy_upgrades)
+    }
+}
+
+#[derive(Clone)]
+pub struct TokensCsvExporter {
+    tokens_writer: SharedWriter,
+}
+
+impl TokensCsvExporter {
+    pub fn new(tokens_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let tokens_writer = create_writer(
+            tokens_output,
+            &[
+                "address",
+                "symbol",
+                "name",
+                "decimals",
+                "total_supply",
+                "block_number"
+            ],
+        )?;
+
+        Ok(TokensCsvExporter { tokens_writer })
+    }
+
+    pub fn export_tokens(&self, tokens: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.tokens_writer, tokens)
+    }
+}
+
+#[derive(Clone)]
+pub struct TracesCsvExporter {
+    traces_writer: SharedWriter,
+}
+
+impl TracesCsvExporter {
+    pub fn new(traces_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let traces_writer = create_writer(
+            traces_output,
+            &[
+                "block_number",
+                "transaction_hash",
+                "transaction_index",
+                "from_address",
+                "to_address",
+                "value",
+                "input",
+                "output",
+                "trace_type",
+                "call_type",
+                "reward_type",
+                "gas",
+                "gas_used",
+                "subtraces",
+                "trace_address",
+                "error",
+                "status",
+                "trace_id"
+            ],
+        )?;
+
+        Ok(TracesCsvExporter { traces_writer })
+    }
+
+    pub fn export_traces(&self, traces: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.traces_writer, traces)
+    }
+}
+
+#[derive(Clone)]
+pub struct StateDiffsCsvExporter {
+    balance_changes_writer: SharedWriter,
+    storage_changes_writer: SharedWriter,
+}
+
+impl StateDiffsCsvExporter {
+    pub fn new(
+        balance_changes_output: Option<PathBuf>,
+        storage_changes_output: Option<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
+        let balance_changes_writer = create_writer(
+            balance_changes_output,
+            &[
+                "block_number",
+                "transaction_hash",
+                "transaction_index",
+                "address",
+                "balance_before",
+                "balance_after",
+                "nonce_before",
+                "nonce_after",
+                "code_hash_before",
+                "code_hash_after"
+            ],
+        )?;
+
+        let storage_changes_writer = create_writer(
+            storage_changes_output,
+            &[
+                "block_number",
+                "transaction_hash",
+                "transaction_index",
+                "address",
+                "slot",
+                "value_before",
+                "value_after"
+            ],
+        )?;
+
+        Ok(StateDiffsCsvExporter {
+            balance_changes_writer,
+            storage_changes_writer,
+        })
+    }
+
+    pub fn export_state_diffs(
+        &self,
+        balance_changes: Vec<Vec<String>>,
+        storage_changes: Vec<Vec<String>>,
+    ) -> Result<(), std::io::Error> {
+        write_rows(&self.balance_changes_writer, balance_changes)?;
+        write_rows(&self.storage_changes_writer, storage_changes)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct AccountStateCsvExporter {
+    account_state_writer: SharedWriter,
+}
+
+impl AccountStateCsvExporter {
+    pub fn new(account_state_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let account_state_writer = create_writer(
+            account_state_output,
+            &[
+                "block_number",
+                "address",
+                "balance",
+                "nonce",
+                "code_hash"
+            ],
+        )?;
+
+        Ok(AccountStateCsvExporter { account_state_writer })
+    }
+
+    pub fn export_account_state(&self, account_state: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.account_state_writer, account_state)
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenBalancesCsvExporter {
+    token_balances_writer: SharedWriter,
+}
+
+impl TokenBalancesCsvExporter {
+    pub fn new(token_balances_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let token_balances_writer = create_writer(
+            token_balances_output,
+            &["block_number", "token_address", "holder_address", "balance"],
+        )?;
+
+        Ok(TokenBalancesCsvExporter { token_balances_writer })
+    }
+
+    pub fn export_token_balances(&self, token_balances: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.token_balances_writer, token_balances)
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestsCsvExporter {
+    requests_writer: SharedWriter,
+}
+
+impl RequestsCsvExporter {
+    pub fn new(requests_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        let requests_writer = create_writer(
+            requests_output,
+            &[
+                "block_number",
+                "block_hash",
+                "request_type",
+                "request_index",
+                "source_address",
+                "pubkey",
+                "target_pubkey",
+                "withdrawal_credentials",
+                "amount",
+                "signature",
+                "deposit_index"
+            ],
+        )?;
+
+        Ok(RequestsCsvExporter { requests_writer })
+    }
+
+    pub fn export_requests(&self, requests: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.requests_writer, requests)
+    }
+}
+
+/// Writes decoded logs to one `<table>.csv` per event in an output directory. Files are created
+/// when the first log of their event is decoded, so large ABI directories do not leave empty
+/// tables behind. Logs that match no event go to the residual file in the logs format.
+#[derive(Clone)]
+pub struct DecodedLogsCsvExporter {
+    output_dir: PathBuf,
+    table_writers: Arc<Mutex<HashMap<String, Writer<File>>>>,
+    residual_writer: SharedWriter,
+}
+
+impl DecodedLogsCsvExporter {
+    pub fn new(output_dir: PathBuf, residual_output: Option<PathBuf>) -> Result<Self, std::io::Error> {
+        std::fs::create_dir_all(&output_dir)?;
+        let residual_writer = create_writer(residual_output, &LOG_COLUMNS)?;
+
+        Ok(DecodedLogsCsvExporter {
+            output_dir,
+            table_writers: Arc::new(Mutex::new(HashMap::new())),
+            residual_writer,
+        })
+    }
+
+    pub fn export_decoded_logs(
+        &self,
+        table: &str,
+        header: &[String],
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), std::io::Error> {
+        let mut table_writers = self.table_writers.lock().unwrap();
+        let writer = match table_writers.entry(table.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut writer = csv::Writer::from_path(self.output_dir.join(format!("{}.csv", table)))?;
+                writer.write_record(header)?;
+                entry.insert(writer)
             }
-            writer.flush()?;
+        };
+
+        for row in rows {
+            writer.write_record(&row)?;
         }
+        writer.flush()?;
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn export_residual_logs(&self, logs: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.residual_writer, logs)
+    }
+}
+
+#[derive(Clone)]
+pub struct EthSupplyCsvExporter {
+    eth_supply_writer: SharedWriter,
+}
+
+impl EthSupplyCsvExporter {
+    /// With `append`, rows are added to the existing file, which is how a run continues from a
+    /// checkpoint.
+    pub fn new(eth_supply_output: PathBuf, append: bool) -> Result<Self, std::io::Error> {
+        let eth_supply_writer = if append {
+            append_writer(eth_supply_output)?
+        } else {
+            create_writer(
+                Some(eth_supply_output),
+                &[
+                    "block_number",
+                    "block_hash",
+                    "issuance",
+                    "burnt_fees",
+                    "withdrawals",
+                    "blob_fees_burnt",
+                    "supply_delta",
+                    "supply"
+                ],
+            )?
+        };
+
+        Ok(EthSupplyCsvExporter { eth_supply_writer })
+    }
+
+    pub fn export_eth_supply(&self, eth_supply: Vec<Vec<String>>) -> Result<(), std::io::Error> {
+        write_rows(&self.eth_supply_writer, eth_supply)
+    }
+}
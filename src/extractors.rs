@@ -0,0 +1,366 @@
+use crate::domain::{Log, TokenTransfer};
+use ethers::abi::{decode, ParamType, Token};
+use ethers::types::U256;
+use tracing::warn;
+
+/// keccak256("Transfer(address,address,uint256)")
+pub const TRANSFER_EVENT_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// keccak256("TransferSingle(address,address,address,uint256,uint256)")
+pub const TRANSFER_SINGLE_EVENT_TOPIC: &str =
+    "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+
+/// keccak256("TransferBatch(address,address,address,uint256[],uint256[])")
+pub const TRANSFER_BATCH_EVENT_TOPIC: &str =
+    "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+pub const TOKEN_TRANSFER_EVENT_TOPICS: [&str; 3] = [
+    TRANSFER_EVENT_TOPIC,
+    TRANSFER_SINGLE_EVENT_TOPIC,
+    TRANSFER_BATCH_EVENT_TOPIC,
+];
+
+/// Decodes a token transfer log into one token transfer per transferred (id, value) pair.
+///
+/// Handles ERC-20/ERC-721 `Transfer` and ERC-1155 `TransferSingle`/`TransferBatch`; any other
+/// log yields nothing.
+pub fn extract_token_transfers(log: &Log) -> Vec<TokenTransfer> {
+    let topic0 = match log.topics.first() {
+        Some(topic) => topic.to_lowercase(),
+        None => return vec![],
+    };
+
+    let token_transfers = match topic0.as_str() {
+        TRANSFER_EVENT_TOPIC => extract_transfer(log).map(|transfer| vec![transfer]),
+        TRANSFER_SINGLE_EVENT_TOPIC => extract_transfer_single(log).map(|transfer| vec![transfer]),
+        TRANSFER_BATCH_EVENT_TOPIC => extract_transfer_batch(log),
+        _ => return vec![],
+    };
+
+    token_transfers.unwrap_or_else(|| {
+        warn!(
+            "Skipping malformed token transfer log {:?} of transaction {:?}",
+            log.log_index, log.transaction_hash
+        );
+        vec![]
+    })
+}
+
+/// ERC-20 emits `value` in the log data (3 topics) while ERC-721 indexes `tokenId` (4 topics).
+/// Some early tokens index nothing and put every argument in the data, so topics and data
+/// words are read as one sequence and the log is decoded as long as it holds exactly 4 words.
+fn extract_transfer(log: &Log) -> Option<TokenTransfer> {
+    let mut words = topics_to_words(&log.topics)?;
+    words.extend(split_to_words(&log.data)?);
+
+    if words.len() != 4 {
+        return None;
+    }
+
+    let value = U256::from_big_endian(&words[3]).to_string();
+    let (token_standard, token_id) = if log.topics.len() == 4 {
+        ("ERC721", Some(value.clone()))
+    } else {
+        ("ERC20", None)
+    };
+
+    Some(TokenTransfer {
+        token_address: log.address.clone(),
+        from_address: word_to_address(&words[1]),
+        to_address: word_to_address(&words[2]),
+        value,
+        transaction_hash: log.transaction_hash.clone(),
+        log_index: log.log_index,
+        block_number: log.block_number,
+        operator: None,
+        token_id,
+        batch_index: None,
+        token_standard: token_standard.to_string(),
+    })
+}
+
+/// `TransferSingle` indexes operator, from and to, and carries `id` and `value` in the data.
+fn extract_transfer_single(log: &Log) -> Option<TokenTransfer> {
+    let topics = topics_to_words(&log.topics)?;
+    let data = split_to_words(&log.data)?;
+
+    if topics.len() != 4 || data.len() != 2 {
+        return None;
+    }
+
+    Some(erc1155_transfer(
+        log,
+        &topics,
+        U256::from_big_endian(&data[0]),
+        U256::from_big_endian(&data[1]),
+        None,
+    ))
+}
+
+/// `TransferBatch` carries two dynamic `uint256[]` arrays in the data, so it goes through the
+/// ABI decoder to follow the head offsets rather than assuming a fixed layout.
+fn extract_transfer_batch(log: &Log) -> Option<Vec<TokenTransfer>> {
+    let topics = topics_to_words(&log.topics)?;
+    if topics.len() != 4 {
+        return None;
+    }
+
+    let data = hex::decode(log.data.trim_start_matches("0x")).ok()?;
+    let uint_array = || ParamType::Array(Box::new(ParamType::Uint(256)));
+    let mut tokens = decode(&[uint_array(), uint_array()], &data).ok()?.into_iter();
+
+    let ids = tokens.next()?.into_array()?;
+    let values = tokens.next()?.into_array()?;
+    if ids.len() != values.len() {
+        return None;
+    }
+
+    ids.into_iter()
+        .zip(values)
+        .enumerate()
+        .map(|(batch_index, (id, value))| {
+            Some(erc1155_transfer(
+                log,
+                &topics,
+                Token::into_uint(id)?,
+                Token::into_uint(value)?,
+                Some(batch_index as u64),
+            ))
+        })
+        .collect()
+}
+
+fn erc1155_transfer(
+    log: &Log,
+    topics: &[[u8; 32]],
+    id: U256,
+    value: U256,
+    batch_index: Option<u64>,
+) -> TokenTransfer {
+    TokenTransfer {
+        token_address: log.address.clone(),
+        from_address: word_to_address(&topics[2]),
+        to_address: word_to_address(&topics[3]),
+        value: value.to_string(),
+        transaction_hash: log.transaction_hash.clone(),
+        log_index: log.log_index,
+        block_number: log.block_number,
+        operator: Some(word_to_address(&topics[1])),
+        token_id: Some(id.to_string()),
+        batch_index,
+        token_standard: "ERC1155".to_string(),
+    }
+}
+
+fn topics_to_words(topics: &[String]) -> Option<Vec<[u8; 32]>> {
+    topics.iter().map(|topic| word_from_hex(topic)).collect()
+}
+
+fn word_from_hex(value: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(value.trim_start_matches("0x")).ok()?;
+    bytes.try_into().ok()
+}
+
+fn split_to_words(data: &str) -> Option<Vec<[u8; 32]>> {
+    let bytes = hex::decode(data.trim_start_matches("0x")).ok()?;
+    if bytes.len() % 32 != 0 {
+        return None;
+    }
+
+    Some(
+        bytes
+            .chunks_exact(32)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect(),
+    )
+}
+
+fn word_to_address(word: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(&word[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::encode;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const BAYC: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+    const OPENSEA_SHARED_STOREFRONT: &str = "0x495f947276749ce646f68ac8c248420045cb7b5e";
+    const OPERATOR: &str = "0x1e0049783f008a0085193e00003d00cd54003c71";
+    const FROM: &str = "0x28c6c06298d514db089934071355e5743bf21d60";
+    const TO: &str = "0x21a31ee1afc51d94c2efccaa2092ad1028285549";
+    const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+    fn address_topic(address: &str) -> String {
+        format!("0x000000000000000000000000{}", address.trim_start_matches("0x"))
+    }
+
+    fn uint_word(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    fn log(address: &str, topics: Vec<String>, data: &str) -> Log {
+        Log {
+            log_index: Some(7),
+            transaction_hash: Some("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060".to_string()),
+            transaction_index: Some(3),
+            block_hash: None,
+            block_number: Some(17_000_000),
+            address: address.to_string(),
+            data: data.to_string(),
+            topics,
+        }
+    }
+
+    fn erc1155_topics(event_topic: &str) -> Vec<String> {
+        vec![event_topic.to_string(), address_topic(OPERATOR), address_topic(FROM), address_topic(TO)]
+    }
+
+    fn transfer_batch(ids: &[u64], values: &[u64]) -> Log {
+        let uint_array = |numbers: &[u64]| Token::Array(numbers.iter().map(|&number| Token::Uint(number.into())).collect());
+        let data = format!("0x{}", hex::encode(encode(&[uint_array(ids), uint_array(values)])));
+        log(OPENSEA_SHARED_STOREFRONT, erc1155_topics(TRANSFER_BATCH_EVENT_TOPIC), &data)
+    }
+
+    #[test]
+    fn extracts_an_erc20_transfer() {
+        // 1,500 USDC
+        let log = log(
+            USDC,
+            vec![TRANSFER_EVENT_TOPIC.to_string(), address_topic(FROM), address_topic(TO)],
+            &format!("0x{}", uint_word(1_500_000_000)),
+        );
+
+        let transfers = extract_token_transfers(&log);
+
+        assert_eq!(transfers.len(), 1);
+        let transfer = &transfers[0];
+        assert_eq!(transfer.token_standard, "ERC20");
+        assert_eq!(transfer.token_address, USDC);
+        assert_eq!(transfer.from_address, FROM);
+        assert_eq!(transfer.to_address, TO);
+        assert_eq!(transfer.value, "1500000000");
+        assert_eq!(transfer.token_id, None);
+        assert_eq!(transfer.operator, None);
+        assert_eq!(transfer.log_index, Some(7));
+        assert_eq!(transfer.block_number, Some(17_000_000));
+    }
+
+    #[test]
+    fn extracts_an_erc721_transfer_from_the_indexed_token_id() {
+        // The mint of BAYC #0
+        let log = log(
+            BAYC,
+            vec![
+                TRANSFER_EVENT_TOPIC.to_string(),
+                address_topic(ZERO_ADDRESS),
+                address_topic("0xaba7161a7fb69c88e16ed9f455ce62b791ee4d03"),
+                format!("0x{}", uint_word(0)),
+            ],
+            "0x",
+        );
+
+        let transfers = extract_token_transfers(&log);
+
+        assert_eq!(transfers.len(), 1);
+        let transfer = &transfers[0];
+        assert_eq!(transfer.token_standard, "ERC721");
+        assert_eq!(transfer.from_address, ZERO_ADDRESS);
+        assert_eq!(transfer.to_address, "0xaba7161a7fb69c88e16ed9f455ce62b791ee4d03");
+        assert_eq!(transfer.token_id.as_deref(), Some("0"));
+        assert_eq!(transfer.value, "0");
+    }
+
+    #[test]
+    fn reads_transfers_that_index_nothing_from_the_data() {
+        let log = log(
+            "0x06012c8cf97bead5deae237070f9587f8e7a266d",
+            vec![TRANSFER_EVENT_TOPIC.to_string()],
+            &format!("0x{}{}{}", &address_topic(FROM)[2..], &address_topic(TO)[2..], uint_word(1_024)),
+        );
+
+        let transfers = extract_token_transfers(&log);
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].from_address, FROM);
+        assert_eq!(transfers[0].to_address, TO);
+        assert_eq!(transfers[0].value, "1024");
+    }
+
+    #[test]
+    fn extracts_an_erc1155_transfer_single() {
+        let log = log(
+            OPENSEA_SHARED_STOREFRONT,
+            erc1155_topics(TRANSFER_SINGLE_EVENT_TOPIC),
+            &format!("0x{}{}", uint_word(42), uint_word(3)),
+        );
+
+        let transfers = extract_token_transfers(&log);
+
+        assert_eq!(transfers.len(), 1);
+        let transfer = &transfers[0];
+        assert_eq!(transfer.token_standard, "ERC1155");
+        assert_eq!(transfer.operator.as_deref(), Some(OPERATOR));
+        assert_eq!(transfer.from_address, FROM);
+        assert_eq!(transfer.to_address, TO);
+        assert_eq!(transfer.token_id.as_deref(), Some("42"));
+        assert_eq!(transfer.value, "3");
+        assert_eq!(transfer.batch_index, None);
+    }
+
+    #[test]
+    fn pairs_the_ids_and_values_of_an_erc1155_transfer_batch_by_index() {
+        let transfers = extract_token_transfers(&transfer_batch(&[1, 2, 3], &[10, 20, 30]));
+
+        let rows = transfers
+            .iter()
+            .map(|transfer| (transfer.batch_index, transfer.token_id.clone().unwrap(), transfer.value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                (Some(0), "1".to_string(), "10".to_string()),
+                (Some(1), "2".to_string(), "20".to_string()),
+                (Some(2), "3".to_string(), "30".to_string()),
+            ]
+        );
+        assert!(transfers.iter().all(|transfer| transfer.token_standard == "ERC1155"
+            && transfer.operator.as_deref() == Some(OPERATOR)
+            && transfer.from_address == FROM
+            && transfer.to_address == TO));
+    }
+
+    #[test]
+    fn skips_malformed_transfer_logs() {
+        assert!(extract_token_transfers(&transfer_batch(&[1, 2], &[10])).is_empty());
+
+        let topics = vec![TRANSFER_EVENT_TOPIC.to_string(), address_topic(FROM), address_topic(TO)];
+        let truncated = log(USDC, topics, "0x05f5e1");
+        assert!(extract_token_transfers(&truncated).is_empty());
+
+        let single_without_value = log(
+            OPENSEA_SHARED_STOREFRONT,
+            erc1155_topics(TRANSFER_SINGLE_EVENT_TOPIC),
+            &format!("0x{}", uint_word(42)),
+        );
+        assert!(extract_token_transfers(&single_without_value).is_empty());
+    }
+
+    #[test]
+    fn ignores_other_events() {
+        let approval = log(
+            USDC,
+            vec![
+                "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925".to_string(),
+                address_topic(FROM),
+                address_topic(TO),
+            ],
+            &format!("0x{}", uint_word(1)),
+        );
+
+        assert!(extract_token_transfers(&approval).is_empty());
+        assert!(extract_token_transfers(&log(USDC, vec![], "0x")).is_empty());
+    }
+}
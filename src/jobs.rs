@@ -1,14 +1,236 @@
-use crate::domain::{Block, Transaction};
-use crate::exporters::CsvExporter;
-use crate::mappers::{block_to_csv_row, ethers_block_to_block, transaction_to_csv_row};
+use crate::calldata_decoder::CalldataDecoder;
+use crate::chain_config::ChainConfig;
+use crate::domain::{
+    AccountState, BalanceChange, Block, ExecutionRequest, Log, Receipt, StorageChange, Trace,
+    Uncle,
+};
+use crate::exporters::{
+    AccountStateCsvExporter, ContractsCsvExporter, CsvExporter, DecodedLogsCsvExporter,
+    EthSupplyCsvExporter, ReceiptsCsvExporter, RequestsCsvExporter, StateDiffsCsvExporter,
+    TokenBalancesCsvExporter, TokenTransfersCsvExporter, TokensCsvExporter, TracesCsvExporter,
+};
+use crate::extractors::{extract_token_transfers, TOKEN_TRANSFER_EVENT_TOPICS};
+use crate::log_decoder::LogDecoder;
+use crate::mappers::{
+    account_state_to_csv_row, balance_change_to_csv_row, block_reward_to_csv_row, block_to_csv_row,
+    bytecode_to_contract, calculate_transaction_fees, contract_to_csv_row,
+    enrich_transaction_with_receipt, enrich_transaction_with_recovered_sender,
+    eth_supply_to_csv_row, ethers_block_to_block, ethers_log_to_log, ethers_receipt_to_receipt,
+    ethers_trace_to_trace, ethers_uncle_to_uncle, execution_request_to_csv_row, log_csv_row_to_log,
+    log_to_csv_row, proxy_upgrade_to_csv_row, receipt_to_csv_row, storage_change_to_csv_row,
+    token_balance_to_csv_row, token_to_csv_row, token_transfer_to_csv_row, trace_to_csv_row,
+    transaction_access_list_entry_to_csv_row, transaction_authorization_to_csv_row,
+    transaction_to_access_list_entries, transaction_to_authorizations, transaction_to_csv_row,
+    uncle_to_csv_row, withdrawal_to_csv_row, LogCsvRow,
+};
+use crate::proxies::{
+    detect_proxy, log_to_proxy_upgrade, BEACON_UPGRADED_EVENT_TOPIC, UPGRADED_EVENT_TOPIC,
+};
+use crate::requests::{
+    block_requests, requests_hash, BlockRequestSources, CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS,
+    DEPOSIT_EVENT_TOPIC, SYSTEM_ADDRESS, WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS,
+};
+use crate::rewards::calculate_block_reward;
+use crate::signatures::recover_sender;
+use crate::state_diffs::{
+    geth_state_diff_to_changes, parity_state_diff_to_changes, GethTransactionStateDiff,
+    TransactionRef,
+};
+use crate::supply::{apply_supply_changes, block_supply_changes, SupplyChanges};
+use crate::tokens::{fetch_token_balances, is_multicall3_deployed, TokenService};
+use crate::traces::{
+    calculate_trace_ids, calculate_trace_statuses, flatten_call_frame, GethTransactionTrace,
+    TraceSource,
+};
 use crate::utils::ProgressTracker;
-use anyhow::{Context, Result};
-use ethers::providers::Middleware;
-use ethers::types::BlockNumber;
+use anyhow::{bail, Context, Result};
+use ethers::providers::{JsonRpcError, Middleware, MiddlewareError};
+use ethers::types::{
+    Action, Address, BlockNumber, Filter, Res, TraceType, TransactionReceipt, TransactionRequest,
+    H256, U256,
+};
+use ethers::utils::{get_contract_address, keccak256};
 use futures::future::join_all;
-use std::convert::TryFrom;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::{info, instrument};
+use tokio::sync::Semaphore;
+use tracing::{info, instrument, warn};
+
+/// Runs `task` for every block in `start_block..=end_block`.
+///
+/// At most `max_workers` tasks run at the same time and progress is reported every `batch_size`
+/// blocks. Each task returns the number of items it exported; the sum is returned.
+async fn for_each_block<F, Fut>(
+    name: &str,
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    max_workers: usize,
+    task: F,
+) -> Result<u64>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<u64>> + Send + 'static,
+{
+    let total_blocks = end_block - start_block + 1;
+    for_each_item(name, start_block..=end_block, total_blocks, batch_size, max_workers, task).await
+}
+
+/// Same as [`for_each_block`] for jobs that are driven by something other than a block range,
+/// such as a list of addresses.
+async fn for_each_item<T, I, F, Fut>(
+    name: &str,
+    items: I,
+    total_items: u64,
+    batch_size: u64,
+    max_workers: usize,
+    task: F,
+) -> Result<u64>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<u64>> + Send + 'static,
+{
+    let mut progress = ProgressTracker::new(name, Some(total_items));
+    progress.start();
+
+    let semaphore = Arc::new(Semaphore::new(max_workers.max(1)));
+    let mut futures = vec![];
+    let mut items_count = 0;
+
+    for item in items {
+        let permit = semaphore.clone().acquire_owned().await?;
+        let future = task(item);
+
+        futures.push(tokio::spawn(async move {
+            let result = future.await;
+            drop(permit);
+            result
+        }));
+
+        if futures.len() >= batch_size as usize {
+            let batch_len = futures.len() as u64;
+            for result in join_all(futures.drain(..)).await {
+                items_count += result??;
+            }
+            progress.track(batch_len);
+        }
+    }
+
+    if !futures.is_empty() {
+        let batch_len = futures.len() as u64;
+        for result in join_all(futures).await {
+            items_count += result??;
+        }
+        progress.track(batch_len);
+    }
+
+    progress.finish();
+    Ok(items_count)
+}
+
+/// Fetches all receipts of a block with `eth_getBlockReceipts`.
+///
+/// Nodes that do not implement the batch method are remembered through `block_receipts_supported`,
+/// and receipts are fetched one by one with `eth_getTransactionReceipt` from then on. Any other
+/// error response, such as a rate limit or a pruned block, is returned.
+async fn fetch_block_receipts<M>(
+    provider: &M,
+    block_number: u64,
+    block_receipts_supported: &AtomicBool,
+) -> Result<Vec<TransactionReceipt>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    if block_receipts_supported.load(Ordering::Relaxed) {
+        match provider.get_block_receipts(block_number).await {
+            Ok(receipts) => return Ok(receipts),
+            Err(e) if e.as_error_response().is_some_and(is_method_unsupported) => {
+                warn!(
+                    "eth_getBlockReceipts is not available ({}), falling back to eth_getTransactionReceipt",
+                    e
+                );
+                block_receipts_supported.store(false, Ordering::Relaxed);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let block = provider
+        .get_block(block_number)
+        .await?
+        .context("Block not found")?;
+
+    let mut receipts = Vec::with_capacity(block.transactions.len());
+    for tx_hash in block.transactions {
+        let receipt = provider
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .with_context(|| format!("Receipt not found for transaction {:#x}", tx_hash))?;
+        receipts.push(receipt);
+    }
+
+    Ok(receipts)
+}
+
+/// Adds the receipt fields to the transactions of a block.
+fn enrich_block_with_receipts(block: &mut Block, receipts_by_hash: &HashMap<String, Receipt>) -> Result<()> {
+    for transaction in block.transactions.iter_mut() {
+        let receipt = receipts_by_hash
+            .get(&transaction.hash)
+            .with_context(|| format!("Receipt not found for transaction {}", transaction.hash))?;
+        enrich_transaction_with_receipt(transaction, receipt);
+    }
+
+    Ok(())
+}
+
+/// JSON-RPC code of a method the node does not implement.
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+/// Whether an error response says the node does not implement the called method. Some providers
+/// answer with their own code, so the message is checked as well.
+fn is_method_unsupported(error: &JsonRpcError) -> bool {
+    let message = error.message.to_lowercase();
+    error.code == METHOD_NOT_FOUND_CODE
+        || message.contains("method not found")
+        || message.contains("method not supported")
+        || message.contains("does not exist")
+}
+
+/// keccak256(rlp([])), the `sha3_uncles` of every block without uncles.
+const EMPTY_UNCLES_HASH: &str = "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347";
+
+/// Fetches the uncle headers of a block with `eth_getUncleByBlockHashAndIndex`.
+async fn fetch_uncles<M>(provider: &M, block: &Block, uncles_count: u64) -> Result<Vec<Uncle>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    if block.sha3_uncles == EMPTY_UNCLES_HASH {
+        return Ok(vec![]);
+    }
+
+    let block_hash: H256 = block.hash.parse()?;
+    let mut uncles = Vec::with_capacity(uncles_count as usize);
+    for uncle_index in 0..uncles_count {
+        let ethers_uncle = provider
+            .get_uncle(block_hash, uncle_index.into())
+            .await?
+            .with_context(|| format!("Uncle {} not found in block {}", uncle_index, block.number))?;
+        let uncle = ethers_uncle_to_uncle(&ethers_uncle, block, uncle_index)
+            .with_context(|| format!("Incomplete uncle {} in block {}", uncle_index, block.number))?;
+        uncles.push(uncle);
+    }
+
+    Ok(uncles)
+}
 
 pub struct ExportBlocksJob<M: Middleware> {
     start_block: u64,
@@ -17,6 +239,10 @@ pub struct ExportBlocksJob<M: Middleware> {
     provider: M,
     max_workers: usize,
     exporter: CsvExporter,
+    with_receipts: bool,
+    verify_signatures: bool,
+    calldata_decoder: Option<Arc<CalldataDecoder>>,
+    chain_config: ChainConfig,
 }
 
 impl<M> ExportBlocksJob<M>
@@ -24,6 +250,7 @@ where
     M: Middleware + 'static + Clone,
     M::Error: 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_block: u64,
         end_block: u64,
@@ -31,6 +258,10 @@ where
         provider: M,
         max_workers: usize,
         exporter: CsvExporter,
+        with_receipts: bool,
+        verify_signatures: bool,
+        calldata_decoder: Option<CalldataDecoder>,
+        chain_config: ChainConfig,
     ) -> Self {
         Self {
             start_block,
@@ -39,78 +270,1645 @@ where
             provider,
             max_workers,
             exporter,
+            with_receipts,
+            verify_signatures,
+            calldata_decoder: calldata_decoder.map(Arc::new),
+            chain_config,
         }
     }
 
     #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
     pub async fn run(&self) -> Result<()> {
         let start_time = Instant::now();
+        let with_receipts = self.with_receipts;
+        // Block rewards need the fees paid, which need the receipts even when the transactions
+        // are exported without their receipt columns
+        let fetches_receipts = with_receipts || self.exporter.exports_block_rewards();
+        let verify_signatures = self.verify_signatures;
+        let block_receipts_supported = Arc::new(AtomicBool::new(true));
 
-        let total_blocks = self.end_block - self.start_block + 1;
-        let mut progress = ProgressTracker::new("export_blocks_and_transactions", Some(total_blocks));
-        progress.start();
+        let transactions_count = for_each_block(
+            "export_blocks_and_transactions",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+                let block_receipts_supported = block_receipts_supported.clone();
+                let calldata_decoder = self.calldata_decoder.clone();
+                let chain_config = self.chain_config.clone();
 
-        let block_range = self.start_block..=self.end_block;
-        let mut futures = vec![];
-        let mut blocks_count = 0;
-        let mut transactions_count = 0;
+                async move {
+                    let block = provider
+                        .get_block_with_txs(block_number)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting block: {}", e);
+                            e
+                        })?
+                        .context("Block not found")?;
 
-        for block_number in block_range {
-            let provider = self.provider.clone();
-            let exporter = self.exporter.clone();
+                    let uncles_count = block.uncles.len() as u64;
+                    let recovered_senders = verify_signatures
+                        .then(|| block.transactions.iter().map(recover_sender).collect::<Vec<_>>());
+                    let mut block = ethers_block_to_block(block).unwrap();
 
-            futures.push(tokio::spawn(async move {
-                let block = provider
-                    .get_block_with_txs(block_number)
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("Error getting block: {}", e);
-                        e
-                    })?
-                    .context("Block not found")?;
+                    for (transaction, recovered_sender) in block.transactions.iter_mut().zip(recovered_senders.into_iter().flatten()) {
+                        enrich_transaction_with_recovered_sender(transaction, recovered_sender);
+                        if transaction.from_address_mismatch == Some(true) {
+                            tracing::warn!(
+                                "Sender mismatch in transaction {}: node reports {}, signature recovers {}",
+                                transaction.hash,
+                                transaction.from_address,
+                                transaction.recovered_from_address.as_deref().unwrap_or("nothing")
+                            );
+                        }
+                    }
+
+                    if let Some(calldata_decoder) = &calldata_decoder {
+                        // The input of a contract creation is init code, not a call
+                        for transaction in block.transactions.iter_mut().filter(|tx| tx.to_address.is_some()) {
+                            if let Some(call) = calldata_decoder.decode(&transaction.input) {
+                                transaction.method_signature = Some(call.method_signature);
+                                transaction.method_name = Some(call.method_name);
+                                transaction.decoded_args = call.decoded_args;
+                            }
+                        }
+                    }
+
+                    let receipts_by_hash = if fetches_receipts {
+                        let receipts = fetch_block_receipts(&provider, block_number, &block_receipts_supported)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error getting receipts for block {}: {}", block_number, e);
+                                e
+                            })?;
+                        receipts
+                            .into_iter()
+                            .map(ethers_receipt_to_receipt)
+                            .map(|receipt| (receipt.transaction_hash.clone(), receipt))
+                            .collect::<HashMap<_, _>>()
+                    } else {
+                        HashMap::new()
+                    };
+
+                    // The rewards are computed on a copy so that exporting them leaves the
+                    // transaction columns as they are without --with-receipts
+                    let mut rewarded_block = None;
+                    if with_receipts {
+                        enrich_block_with_receipts(&mut block, &receipts_by_hash)?;
+                    } else if fetches_receipts {
+                        let mut block = block.clone();
+                        enrich_block_with_receipts(&mut block, &receipts_by_hash)?;
+                        rewarded_block = Some(block);
+                    }
+                    for block in std::iter::once(&mut block).chain(rewarded_block.as_mut()) {
+                        for transaction in block.transactions.iter_mut() {
+                            calculate_transaction_fees(transaction, block.base_fee_per_gas);
+                        }
+                    }
 
-                let block = ethers_block_to_block(block).unwrap();
+                    let block_csv_row = block_to_csv_row(&block);
+                    let transactions_csv_rows = block.transactions
+                        .iter()
+                        .map(transaction_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+                    let withdrawals_csv_rows = block.withdrawals
+                        .iter()
+                        .map(withdrawal_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+                    let access_list_csv_rows = block.transactions
+                        .iter()
+                        .flat_map(transaction_to_access_list_entries)
+                        .map(|entry| transaction_access_list_entry_to_csv_row(&entry))
+                        .collect::<Vec<Vec<String>>>();
+                    let authorizations_csv_rows = block.transactions
+                        .iter()
+                        .flat_map(transaction_to_authorizations)
+                        .map(|authorization| transaction_authorization_to_csv_row(&authorization))
+                        .collect::<Vec<Vec<String>>>();
 
-                let block_csv_row = block_to_csv_row(&block);
-                let transactions_csv_rows = block.transactions
-                    .iter()
-                    .map(transaction_to_csv_row)
-                    .collect::<Vec<Vec<String>>>();
+                    let tx_count = block.transactions.len() as u64;
 
-                let tx_count = block.transactions.len();
+                    let block_result = exporter.export_blocks_and_transactions(
+                        vec![block_csv_row],
+                        transactions_csv_rows,
+                        withdrawals_csv_rows,
+                    );
+                    if let Err(e) = block_result {
+                        tracing::error!("Error exporting block and transactions: {}", e);
+                    }
+                    if let Err(e) = exporter.export_access_list(access_list_csv_rows) {
+                        tracing::error!("Error exporting access lists: {}", e);
+                    }
+                    if let Err(e) = exporter.export_authorizations(authorizations_csv_rows) {
+                        tracing::error!("Error exporting authorizations: {}", e);
+                    }
 
-                let block_result = exporter.export_blocks_and_transactions(vec![block_csv_row], transactions_csv_rows);
-                if let Err(e) = block_result {
-                    tracing::error!("Error exporting block and transactions: {}", e);
+                    if exporter.exports_uncles() || exporter.exports_block_rewards() {
+                        let uncles = fetch_uncles(&provider, &block, uncles_count)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error getting uncles for block {}: {}", block_number, e);
+                                e
+                            })?;
+                        let uncles_csv_rows = uncles
+                            .iter()
+                            .map(uncle_to_csv_row)
+                            .collect::<Vec<Vec<String>>>();
+
+                        if let Err(e) = exporter.export_uncles(uncles_csv_rows) {
+                            tracing::error!("Error exporting uncles: {}", e);
+                        }
+
+                        let rewarded_block = rewarded_block.as_ref().unwrap_or(&block);
+                        let block_reward = calculate_block_reward(rewarded_block, &uncles, &chain_config);
+                        if let Err(e) = exporter.export_block_rewards(vec![block_reward_to_csv_row(&block_reward)]) {
+                            tracing::error!("Error exporting block rewards: {}", e);
+                        }
+                    }
+
+                    Ok(tx_count)
                 }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total transactions: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            transactions_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+pub struct ExportReceiptsJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: ReceiptsCsvExporter,
+}
+
+impl<M> ExportReceiptsJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: ReceiptsCsvExporter,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let block_receipts_supported = Arc::new(AtomicBool::new(true));
+
+        let receipts_count = for_each_block(
+            "export_receipts_and_logs",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+                let block_receipts_supported = block_receipts_supported.clone();
+
+                async move {
+                    let receipts = fetch_block_receipts(&provider, block_number, &block_receipts_supported)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting receipts for block {}: {}", block_number, e);
+                            e
+                        })?;
+
+                    let receipts = receipts
+                        .into_iter()
+                        .map(ethers_receipt_to_receipt)
+                        .collect::<Vec<_>>();
 
-                Ok::<_, anyhow::Error>(tx_count)
-            }));
+                    let receipts_csv_rows = receipts
+                        .iter()
+                        .map(receipt_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+                    let logs_csv_rows = receipts
+                        .iter()
+                        .flat_map(|receipt| receipt.logs.iter().map(log_to_csv_row))
+                        .collect::<Vec<Vec<String>>>();
 
-            if futures.len() >= self.batch_size as usize {
-                let results = join_all(futures.drain(..)).await;
-                for result in results {
-                    let tx_count = result??;
-                    transactions_count += tx_count;
+                    let receipts_count = receipts.len() as u64;
+
+                    let result = exporter.export_receipts_and_logs(receipts_csv_rows, logs_csv_rows);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting receipts and logs: {}", e);
+                    }
+
+                    Ok(receipts_count)
                 }
-                blocks_count += self.batch_size;
-                progress.track(self.batch_size);
-            }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total receipts: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            receipts_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+pub struct ExtractTokenTransfersJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: TokenTransfersCsvExporter,
+}
+
+impl<M> ExtractTokenTransfersJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: TokenTransfersCsvExporter,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let transfer_topics = TOKEN_TRANSFER_EVENT_TOPICS
+            .iter()
+            .map(|topic| topic.parse::<H256>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let token_transfers_count = for_each_block(
+            "extract_token_transfers",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+                let filter = Filter::new().select(block_number).topic0(transfer_topics.clone());
+
+                async move {
+                    let logs = provider
+                        .get_logs(&filter)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting logs for block {}: {}", block_number, e);
+                            e
+                        })?;
+
+                    let token_transfers_csv_rows = logs
+                        .into_iter()
+                        .map(ethers_log_to_log)
+                        .flat_map(|log| extract_token_transfers(&log))
+                        .map(|token_transfer| token_transfer_to_csv_row(&token_transfer))
+                        .collect::<Vec<Vec<String>>>();
+
+                    let token_transfers_count = token_transfers_csv_rows.len() as u64;
+
+                    let result = exporter.export_token_transfers(token_transfers_csv_rows);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting token transfers: {}", e);
+                    }
+
+                    Ok(token_transfers_count)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total token transfers: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            token_transfers_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+/// Extracts token transfers offline from a logs CSV written by `export_receipts_and_logs`.
+pub struct ExtractTokenTransfersFromLogsJob {
+    logs_input: PathBuf,
+    batch_size: u64,
+    exporter: TokenTransfersCsvExporter,
+}
+
+impl ExtractTokenTransfersFromLogsJob {
+    pub fn new(logs_input: PathBuf, batch_size: u64, exporter: TokenTransfersCsvExporter) -> Self {
+        Self {
+            logs_input,
+            batch_size,
+            exporter,
         }
+    }
+
+    #[instrument(skip_all, fields(logs_input = %self.logs_input.display()))]
+    pub fn run(&self) -> Result<()> {
+        let mut reader = csv::Reader::from_path(&self.logs_input)
+            .with_context(|| format!("Failed to open logs file {}", self.logs_input.display()))?;
+
+        let mut progress = ProgressTracker::new("extract_token_transfers", None);
+        progress.start();
+
+        let mut token_transfers_csv_rows = vec![];
+        let mut logs_in_batch = 0;
+        let mut token_transfers_count = 0;
+
+        for row in reader.deserialize::<LogCsvRow>() {
+            let log = log_csv_row_to_log(row?);
+            for token_transfer in extract_token_transfers(&log) {
+                token_transfers_csv_rows.push(token_transfer_to_csv_row(&token_transfer));
+            }
 
-        if !futures.is_empty() {
-            let results = join_all(futures).await;
-            for result in results {
-                let tx_count = result??;
-                transactions_count += tx_count;
+            logs_in_batch += 1;
+            if logs_in_batch >= self.batch_size {
+                token_transfers_count += token_transfers_csv_rows.len();
+                self.exporter.export_token_transfers(std::mem::take(&mut token_transfers_csv_rows))?;
+                progress.track(logs_in_batch);
+                logs_in_batch = 0;
             }
-            blocks_count += 1;
-            progress.track(1);
         }
 
+        token_transfers_count += token_transfers_csv_rows.len();
+        self.exporter.export_token_transfers(token_transfers_csv_rows)?;
+        progress.track(logs_in_batch);
+
+        progress.finish();
+        info!("Finished work. Total token transfers: {}", token_transfers_count);
+
+        Ok(())
+    }
+}
+
+pub struct ExportContractsJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: ContractsCsvExporter,
+    include_traces: bool,
+}
+
+impl<M> ExportContractsJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: ContractsCsvExporter,
+        include_traces: bool,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+            include_traces,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let include_traces = self.include_traces;
+        let upgrade_topics = [UPGRADED_EVENT_TOPIC, BEACON_UPGRADED_EVENT_TOPIC]
+            .iter()
+            .map(|topic| topic.parse::<H256>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let contracts_count = for_each_block(
+            "export_contracts",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+                let upgrades_filter = Filter::new().select(block_number).topic0(upgrade_topics.clone());
+
+                async move {
+                    let addresses = if include_traces {
+                        created_addresses_from_traces(&provider, block_number).await?
+                    } else {
+                        created_addresses_from_transactions(&provider, block_number).await?
+                    };
+
+                    let mut contracts_csv_rows = Vec::with_capacity(addresses.len());
+                    for address in addresses {
+                        let bytecode = provider
+                            .get_code(address, Some(block_number.into()))
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error getting code of {:#x}: {}", address, e);
+                                e
+                            })?;
+
+                        // Failed deployments and self-destructed contracts leave no code behind
+                        if bytecode.is_empty() {
+                            continue;
+                        }
+
+                        let mut contract = bytecode_to_contract(address, &bytecode, block_number);
+                        let proxy = detect_proxy(&provider, address, &bytecode, block_number)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error detecting proxy {:#x}: {}", address, e);
+                                e
+                            })?;
+                        if let Some(proxy) = proxy {
+                            contract.proxy_type = Some(proxy.proxy_type.to_string());
+                            contract.implementation_address = proxy.implementation_address.map(|address| format!("{:#x}", address));
+                            contract.proxy_admin_address = proxy.admin_address.map(|address| format!("{:#x}", address));
+                            contract.beacon_address = proxy.beacon_address.map(|address| format!("{:#x}", address));
+                        }
+                        contracts_csv_rows.push(contract_to_csv_row(&contract));
+                    }
+
+                    let contracts_count = contracts_csv_rows.len() as u64;
+
+                    let result = exporter.export_contracts(contracts_csv_rows);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting contracts: {}", e);
+                    }
+
+                    if exporter.exports_proxy_upgrades() {
+                        let logs = provider
+                            .get_logs(&upgrades_filter)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error getting upgrade logs for block {}: {}", block_number, e);
+                                e
+                            })?;
+                        let proxy_upgrades_csv_rows = logs
+                            .iter()
+                            .filter_map(log_to_proxy_upgrade)
+                            .map(|upgrade| proxy_upgrade_to_csv_row(&upgrade))
+                            .collect::<Vec<Vec<String>>>();
+
+                        let result = exporter.export_proxy_upgrades(proxy_upgrades_csv_rows);
+                        if let Err(e) = result {
+                            tracing::error!("Error exporting proxy upgrades: {}", e);
+                        }
+                    }
+
+                    Ok(contracts_count)
+                }
+            },
+        )
+        .await?;
+
         let duration = start_time.elapsed();
-        info!("Finished work. Total blocks processed: {}. Took: {:?}", blocks_count, duration);
+        info!(
+            "Finished work. Total blocks processed: {}. Total contracts: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            contracts_count,
+            duration
+        );
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Addresses deployed by contract-creation transactions, i.e. transactions without a `to`.
+/// The address follows from the sender and its nonce, so no receipts are needed.
+async fn created_addresses_from_transactions<M>(provider: &M, block_number: u64) -> Result<Vec<Address>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let block = provider
+        .get_block_with_txs(block_number)
+        .await?
+        .context("Block not found")?;
+
+    Ok(block
+        .transactions
+        .iter()
+        .filter(|tx| tx.to.is_none())
+        .map(|tx| get_contract_address(tx.from, tx.nonce))
+        .collect())
+}
+
+/// Addresses deployed by any successful CREATE or CREATE2, including those made by contracts.
+async fn created_addresses_from_traces<M>(provider: &M, block_number: u64) -> Result<Vec<Address>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let traces = provider.trace_block(block_number.into()).await?;
+
+    Ok(traces
+        .into_iter()
+        .filter(|trace| matches!(trace.action, Action::Create(_)) && trace.error.is_none())
+        .filter_map(|trace| match trace.result {
+            Some(Res::Create(result)) => Some(result.address),
+            _ => None,
+        })
+        .collect())
+}
+
+pub struct ExportTokensJob<M: Middleware> {
+    token_addresses: Vec<Address>,
+    block_number: u64,
+    batch_size: u64,
+    max_workers: usize,
+    token_service: TokenService<M>,
+    exporter: TokensCsvExporter,
+}
+
+impl<M> ExportTokensJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        token_addresses: Vec<Address>,
+        block_number: u64,
+        batch_size: u64,
+        max_workers: usize,
+        token_service: TokenService<M>,
+        exporter: TokensCsvExporter,
+    ) -> Self {
+        Self {
+            token_addresses,
+            block_number,
+            batch_size,
+            max_workers,
+            token_service,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(block_number = %self.block_number))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let block_number = self.block_number;
+
+        let result = for_each_item(
+            "export_tokens",
+            self.token_addresses.iter().copied(),
+            self.token_addresses.len() as u64,
+            self.batch_size,
+            self.max_workers,
+            |address| {
+                let token_service = self.token_service.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let token = token_service
+                        .get_token(address, block_number)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting token {:#x}: {}", address, e);
+                            e
+                        })?;
+
+                    let result = exporter.export_tokens(vec![token_to_csv_row(&token)]);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting tokens: {}", e);
+                    }
+
+                    Ok(1)
+                }
+            },
+        )
+        .await;
+
+        // Keep whatever was fetched so that a rerun after a failure starts where this one stopped
+        self.token_service.save_cache()?;
+        let tokens_count = result?;
+
+        let duration = start_time.elapsed();
+        info!("Finished work. Total tokens processed: {}. Took: {:?}", tokens_count, duration);
+
+        Ok(())
+    }
+}
+
+pub struct ExportTracesJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: TracesCsvExporter,
+    trace_source: TraceSource,
+}
+
+impl<M> ExportTracesJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: TracesCsvExporter,
+        trace_source: TraceSource,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+            trace_source,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let trace_source = self.trace_source;
+
+        let traces_count = for_each_block(
+            "export_traces",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let mut traces = fetch_block_traces(&provider, block_number, trace_source)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting traces for block {}: {}", block_number, e);
+                            e
+                        })?;
+                    calculate_trace_statuses(&mut traces);
+                    calculate_trace_ids(&mut traces);
+
+                    let traces_csv_rows = traces
+                        .iter()
+                        .map(trace_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+
+                    let traces_count = traces_csv_rows.len() as u64;
+
+                    let result = exporter.export_traces(traces_csv_rows);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting traces: {}", e);
+                    }
+
+                    Ok(traces_count)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total traces: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            traces_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+async fn fetch_block_traces<M>(provider: &M, block_number: u64, trace_source: TraceSource) -> Result<Vec<Trace>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    match trace_source {
+        TraceSource::Parity => Ok(provider
+            .trace_block(block_number.into())
+            .await?
+            .into_iter()
+            .map(ethers_trace_to_trace)
+            .collect()),
+        TraceSource::Geth => {
+            let tracer_options = serde_json::json!({ "tracer": "callTracer" });
+            let transaction_traces: Vec<GethTransactionTrace> = provider
+                .provider()
+                .request(
+                    "debug_traceBlockByNumber",
+                    (BlockNumber::from(block_number), tracer_options),
+                )
+                .await?;
+
+            let transaction_hashes = resolve_transaction_hashes(
+                provider,
+                block_number,
+                transaction_traces.iter().map(|trace| trace.tx_hash).collect(),
+            )
+            .await?;
+
+            let traces = transaction_traces
+                .iter()
+                .zip(transaction_hashes)
+                .enumerate()
+                .flat_map(|(transaction_index, (transaction_trace, transaction_hash))| {
+                    flatten_call_frame(
+                        &transaction_trace.result,
+                        block_number,
+                        &format!("{:#x}", transaction_hash),
+                        transaction_index as u64,
+                    )
+                })
+                .collect();
+
+            Ok(traces)
+        }
+    }
+}
+
+/// Per-transaction debug and trace responses do not always say which transaction they belong
+/// to (older Geth versions omit `txHash`); in that case they follow the block's transaction order.
+async fn resolve_transaction_hashes<M>(
+    provider: &M,
+    block_number: u64,
+    transaction_hashes: Vec<Option<H256>>,
+) -> Result<Vec<H256>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    if let Some(transaction_hashes) = transaction_hashes.into_iter().collect::<Option<Vec<_>>>() {
+        return Ok(transaction_hashes);
+    }
+
+    Ok(provider
+        .get_block(block_number)
+        .await?
+        .context("Block not found")?
+        .transactions)
+}
+
+pub struct ExportStateDiffsJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: StateDiffsCsvExporter,
+    state_diff_source: TraceSource,
+}
+
+impl<M> ExportStateDiffsJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: StateDiffsCsvExporter,
+        state_diff_source: TraceSource,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+            state_diff_source,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let state_diff_source = self.state_diff_source;
+
+        let changes_count = for_each_block(
+            "export_state_diffs",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let (balance_changes, storage_changes) =
+                        fetch_state_diffs(&provider, block_number, state_diff_source)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Error getting state diffs for block {}: {}", block_number, e);
+                                e
+                            })?;
+
+                    let balance_changes_csv_rows = balance_changes
+                        .iter()
+                        .map(balance_change_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+                    let storage_changes_csv_rows = storage_changes
+                        .iter()
+                        .map(storage_change_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+
+                    let changes_count = (balance_changes.len() + storage_changes.len()) as u64;
+
+                    let result = exporter.export_state_diffs(balance_changes_csv_rows, storage_changes_csv_rows);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting state diffs: {}", e);
+                    }
+
+                    Ok(changes_count)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total state changes: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            changes_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+async fn fetch_state_diffs<M>(
+    provider: &M,
+    block_number: u64,
+    state_diff_source: TraceSource,
+) -> Result<(Vec<BalanceChange>, Vec<StorageChange>)>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let mut balance_changes = vec![];
+    let mut storage_changes = vec![];
+
+    match state_diff_source {
+        TraceSource::Parity => {
+            let block_traces = provider
+                .trace_replay_block_transactions(block_number.into(), vec![TraceType::StateDiff])
+                .await?;
+            let transaction_hashes = resolve_transaction_hashes(
+                provider,
+                block_number,
+                block_traces.iter().map(|trace| trace.transaction_hash).collect(),
+            )
+            .await?;
+
+            for (transaction_index, (block_trace, transaction_hash)) in
+                block_traces.iter().zip(transaction_hashes).enumerate()
+            {
+                let Some(state_diff) = &block_trace.state_diff else {
+                    continue;
+                };
+                let transaction_hash = format!("{:#x}", transaction_hash);
+                let transaction = TransactionRef {
+                    block_number,
+                    transaction_hash: &transaction_hash,
+                    transaction_index: transaction_index as u64,
+                };
+                let (balance, storage) = parity_state_diff_to_changes(state_diff, &transaction);
+                balance_changes.extend(balance);
+                storage_changes.extend(storage);
+            }
+        }
+        TraceSource::Geth => {
+            let tracer_options = serde_json::json!({
+                "tracer": "prestateTracer",
+                "tracerConfig": { "diffMode": true },
+            });
+            let state_diffs: Vec<GethTransactionStateDiff> = provider
+                .provider()
+                .request(
+                    "debug_traceBlockByNumber",
+                    (BlockNumber::from(block_number), tracer_options),
+                )
+                .await?;
+            let transaction_hashes = resolve_transaction_hashes(
+                provider,
+                block_number,
+                state_diffs.iter().map(|diff| diff.tx_hash).collect(),
+            )
+            .await?;
+
+            for (transaction_index, (state_diff, transaction_hash)) in
+                state_diffs.iter().zip(transaction_hashes).enumerate()
+            {
+                let transaction_hash = format!("{:#x}", transaction_hash);
+                let transaction = TransactionRef {
+                    block_number,
+                    transaction_hash: &transaction_hash,
+                    transaction_index: transaction_index as u64,
+                };
+                let (balance, storage) = geth_state_diff_to_changes(&state_diff.result, &transaction);
+                balance_changes.extend(balance);
+                storage_changes.extend(storage);
+            }
+        }
+    }
+
+    Ok((balance_changes, storage_changes))
+}
+
+pub struct ExportAccountStateJob<M: Middleware> {
+    addresses: Vec<Address>,
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: AccountStateCsvExporter,
+}
+
+impl<M> ExportAccountStateJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        addresses: Vec<Address>,
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: AccountStateCsvExporter,
+    ) -> Self {
+        Self {
+            addresses,
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+
+        let snapshots = (self.start_block..=self.end_block)
+            .flat_map(|block_number| self.addresses.iter().map(move |address| (block_number, *address)));
+        let total_snapshots = (self.end_block - self.start_block + 1) * self.addresses.len() as u64;
+
+        let accounts_count = for_each_item(
+            "export_account_state",
+            snapshots,
+            total_snapshots,
+            self.batch_size,
+            self.max_workers,
+            |(block_number, address)| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let block = Some(block_number.into());
+                    let (balance, nonce, code) = tokio::try_join!(
+                        provider.get_balance(address, block),
+                        provider.get_transaction_count(address, block),
+                        provider.get_code(address, block),
+                    )
+                    .map_err(|e| {
+                        tracing::error!("Error getting state of {:#x} at block {}: {}", address, block_number, e);
+                        e
+                    })?;
+
+                    let account_state = AccountState {
+                        block_number,
+                        address: format!("{:#x}", address),
+                        balance: balance.to_string(),
+                        nonce: nonce.as_u64(),
+                        code_hash: format!("0x{}", hex::encode(keccak256(&code))),
+                    };
+
+                    let result = exporter.export_account_state(vec![account_state_to_csv_row(&account_state)]);
+                    if let Err(e) = result {
+                        tracing::error!("Error exporting account state: {}", e);
+                    }
+
+                    Ok(1)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!("Finished work. Total account snapshots: {}. Took: {:?}", accounts_count, duration);
+
+        Ok(())
+    }
+}
+
+pub struct ExportTokenBalancesJob<M: Middleware> {
+    pairs: Vec<(Address, Address)>,
+    block_number: u64,
+    calls_per_request: usize,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: TokenBalancesCsvExporter,
+}
+
+impl<M> ExportTokenBalancesJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        pairs: Vec<(Address, Address)>,
+        block_number: u64,
+        calls_per_request: usize,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: TokenBalancesCsvExporter,
+    ) -> Self {
+        Self {
+            pairs,
+            block_number,
+            calls_per_request,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(block_number = %self.block_number))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let block_number = self.block_number;
+
+        let use_multicall3 = is_multicall3_deployed(&self.provider, block_number).await?;
+        if !use_multicall3 {
+            info!("Multicall3 is not deployed at block {}, calling balanceOf for each pair", block_number);
+        }
+
+        let chunks = self
+            .pairs
+            .chunks(self.calls_per_request.max(1))
+            .map(|chunk| chunk.to_vec())
+            .collect::<Vec<_>>();
+        let total_chunks = chunks.len() as u64;
+
+        let balances_count = for_each_item(
+            "export_token_balances",
+            chunks,
+            total_chunks,
+            self.batch_size,
+            self.max_workers,
+            |pairs| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let token_balances = fetch_token_balances(&provider, &pairs, block_number, use_multicall3)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting token balances at block {}: {}", block_number, e);
+                            e
+                        })?;
+
+                    let rows = token_balances.iter().map(token_balance_to_csv_row).collect();
+                    if let Err(e) = exporter.export_token_balances(rows) {
+                        tracing::error!("Error exporting token balances: {}", e);
+                    }
+
+                    Ok(token_balances.len() as u64)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!("Finished work. Total token balances: {}. Took: {:?}", balances_count, duration);
+
+        Ok(())
+    }
+}
+
+/// Gathers the EIP-7685 requests of a block. Blocks without `requestsHash` predate Prague and
+/// have none.
+///
+/// The rebuilt requests are checked against the block's `requestsHash`, so a block whose requests
+/// cannot be reproduced fails the export instead of being written incomplete.
+async fn fetch_block_requests<M>(
+    provider: &M,
+    block_number: u64,
+    deposit_contract_address: Address,
+) -> Result<Vec<ExecutionRequest>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let block = provider.get_block(block_number).await?.context("Block not found")?;
+    let Some(expected_requests_hash) = block.other.get("requestsHash") else {
+        return Ok(vec![]);
+    };
+    let expected_requests_hash: H256 = serde_json::from_value(expected_requests_hash.clone())
+        .with_context(|| format!("Invalid requestsHash in block {}", block_number))?;
+    let block_hash = format!("{:#x}", block.hash.context("Block hash missing")?);
+
+    let withdrawal_predeploy: Address = WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS.parse()?;
+    let consolidation_predeploy: Address = CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS.parse()?;
+    let deposit_event_topic: H256 = DEPOSIT_EVENT_TOPIC.parse()?;
+
+    let filter = Filter::new()
+        .select(block_number)
+        .address(vec![deposit_contract_address, withdrawal_predeploy, consolidation_predeploy]);
+    let mut sources = BlockRequestSources::default();
+    for log in provider.get_logs(&filter).await? {
+        let data = log.data.to_vec();
+        if log.address == deposit_contract_address {
+            if log.topics.first() == Some(&deposit_event_topic) {
+                sources.deposit_logs.push(data);
+            }
+        } else if log.address == withdrawal_predeploy {
+            sources.withdrawal_request_logs.push(data);
+        } else if log.address == consolidation_predeploy {
+            sources.consolidation_request_logs.push(data);
+        }
+    }
+
+    let parent_block_number = block_number.saturating_sub(1);
+    let (pending_withdrawal_requests, pending_consolidation_requests) = tokio::try_join!(
+        dequeue_requests(provider, withdrawal_predeploy, parent_block_number),
+        dequeue_requests(provider, consolidation_predeploy, parent_block_number),
+    )?;
+    sources.pending_withdrawal_requests = pending_withdrawal_requests;
+    sources.pending_consolidation_requests = pending_consolidation_requests;
+
+    let requests = block_requests(block_number, &block_hash, &sources);
+    let requests_hash = requests_hash(&requests).context("Failed to encode the requests")?;
+    if requests_hash != expected_requests_hash {
+        bail!(
+            "Requests of block {} hash to {:#x} but the block commits to {:#x}",
+            block_number,
+            requests_hash,
+            expected_requests_hash
+        );
+    }
+
+    Ok(requests)
+}
+
+/// Simulates the end-of-block system call of a request predeploy, which returns the requests at
+/// the head of its queue.
+async fn dequeue_requests<M>(provider: &M, predeploy: Address, block_number: u64) -> Result<Vec<u8>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let system_call = TransactionRequest::new()
+        .from(SYSTEM_ADDRESS.parse::<Address>()?)
+        .to(predeploy)
+        .into();
+    Ok(provider.call(&system_call, Some(block_number.into())).await?.to_vec())
+}
+
+pub struct ExportRequestsJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: RequestsCsvExporter,
+    deposit_contract_address: Address,
+}
+
+impl<M> ExportRequestsJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: RequestsCsvExporter,
+        deposit_contract_address: Address,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+            deposit_contract_address,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let deposit_contract_address = self.deposit_contract_address;
+
+        let requests_count = for_each_block(
+            "export_requests",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let requests = fetch_block_requests(&provider, block_number, deposit_contract_address)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting requests for block {}: {}", block_number, e);
+                            e
+                        })?;
+
+                    let requests_csv_rows = requests
+                        .iter()
+                        .map(execution_request_to_csv_row)
+                        .collect::<Vec<Vec<String>>>();
+
+                    let requests_count = requests_csv_rows.len() as u64;
+
+                    if let Err(e) = exporter.export_requests(requests_csv_rows) {
+                        tracing::error!("Error exporting requests: {}", e);
+                    }
+
+                    Ok(requests_count)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total requests: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            requests_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+/// Decodes logs and writes them to the table of their event, or to the residual file when no
+/// event matches. Returns the number of decoded logs.
+fn decode_and_export_logs(
+    decoder: &LogDecoder,
+    exporter: &DecodedLogsCsvExporter,
+    logs: &[Log],
+) -> Result<u64, std::io::Error> {
+    let mut rows_by_table: BTreeMap<usize, Vec<Vec<String>>> = BTreeMap::new();
+    let mut residual_csv_rows = vec![];
+
+    for log in logs {
+        match decoder.decode(log) {
+            Some((table, row)) => rows_by_table.entry(table).or_default().push(row),
+            None => residual_csv_rows.push(log_to_csv_row(log)),
+        }
+    }
+
+    let mut decoded_count = 0;
+    for (table, rows) in rows_by_table {
+        decoded_count += rows.len() as u64;
+        exporter.export_decoded_logs(decoder.table_name(table), &decoder.table_header(table), rows)?;
+    }
+    exporter.export_residual_logs(residual_csv_rows)?;
+
+    Ok(decoded_count)
+}
+
+pub struct DecodeLogsJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    decoder: Arc<LogDecoder>,
+    exporter: DecodedLogsCsvExporter,
+}
+
+impl<M> DecodeLogsJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        decoder: LogDecoder,
+        exporter: DecodedLogsCsvExporter,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            decoder: Arc::new(decoder),
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+
+        let decoded_count = for_each_block(
+            "decode_logs",
+            self.start_block,
+            self.end_block,
+            self.batch_size,
+            self.max_workers,
+            |block_number| {
+                let provider = self.provider.clone();
+                let decoder = self.decoder.clone();
+                let exporter = self.exporter.clone();
+
+                async move {
+                    let logs = provider
+                        .get_logs(&Filter::new().select(block_number))
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Error getting logs for block {}: {}", block_number, e);
+                            e
+                        })?;
+                    let logs = logs.into_iter().map(ethers_log_to_log).collect::<Vec<_>>();
+
+                    // The files have no resume point, so a failed block stops the job rather
+                    // than leaving rows silently missing
+                    let decoded_count = decode_and_export_logs(&decoder, &exporter, &logs).map_err(|e| {
+                        tracing::error!("Error exporting decoded logs of block {}: {}", block_number, e);
+                        e
+                    })?;
+                    Ok(decoded_count)
+                }
+            },
+        )
+        .await?;
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Total decoded logs: {}. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            decoded_count,
+            duration
+        );
+
+        Ok(())
+    }
+}
+
+pub struct DecodeLogsFromFileJob {
+    logs_input: PathBuf,
+    batch_size: u64,
+    decoder: LogDecoder,
+    exporter: DecodedLogsCsvExporter,
+}
+
+impl DecodeLogsFromFileJob {
+    pub fn new(logs_input: PathBuf, batch_size: u64, decoder: LogDecoder, exporter: DecodedLogsCsvExporter) -> Self {
+        Self {
+            logs_input,
+            batch_size,
+            decoder,
+            exporter,
+        }
+    }
+
+    #[instrument(skip_all, fields(logs_input = %self.logs_input.display()))]
+    pub fn run(&self) -> Result<()> {
+        let mut reader = csv::Reader::from_path(&self.logs_input)
+            .with_context(|| format!("Failed to open logs file {}", self.logs_input.display()))?;
+
+        let mut progress = ProgressTracker::new("decode_logs", None);
+        progress.start();
+
+        let mut logs = vec![];
+        let mut decoded_count = 0;
+
+        for row in reader.deserialize::<LogCsvRow>() {
+            logs.push(log_csv_row_to_log(row?));
+
+            if logs.len() as u64 >= self.batch_size {
+                decoded_count += decode_and_export_logs(&self.decoder, &self.exporter, &logs)?;
+                progress.track(logs.len() as u64);
+                logs.clear();
+            }
+        }
+
+        decoded_count += decode_and_export_logs(&self.decoder, &self.exporter, &logs)?;
+        progress.track(logs.len() as u64);
+
+        progress.finish();
+        info!("Finished work. Total decoded logs: {}", decoded_count);
+
+        Ok(())
+    }
+}
+
+/// Fetches what a block changes in the ETH supply. Uncles are only fetched before the merge, and
+/// receipts only for blocks with blobs.
+async fn fetch_block_supply_changes<M>(
+    provider: &M,
+    block_number: u64,
+    chain_config: &ChainConfig,
+    block_receipts_supported: &AtomicBool,
+) -> Result<SupplyChanges>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let ethers_block = provider
+        .get_block_with_txs(block_number)
+        .await?
+        .context("Block not found")?;
+    let uncles_count = ethers_block.uncles.len() as u64;
+    let block = ethers_block_to_block(ethers_block).context("Incomplete block")?;
+
+    let uncles = if chain_config.is_merged(block_number) {
+        vec![]
+    } else {
+        fetch_uncles(provider, &block, uncles_count).await?
+    };
+
+    let receipts = if block.blob_gas_used.unwrap_or_default() > 0 {
+        fetch_block_receipts(provider, block_number, block_receipts_supported)
+            .await?
+            .into_iter()
+            .map(ethers_receipt_to_receipt)
+            .collect()
+    } else {
+        vec![]
+    };
+
+    Ok(block_supply_changes(&block, &uncles, &receipts, chain_config))
+}
+
+pub struct ExportEthSupplyJob<M: Middleware> {
+    start_block: u64,
+    end_block: u64,
+    batch_size: u64,
+    provider: M,
+    max_workers: usize,
+    exporter: EthSupplyCsvExporter,
+    chain_config: ChainConfig,
+    initial_supply: U256,
+}
+
+impl<M> ExportEthSupplyJob<M>
+where
+    M: Middleware + 'static + Clone,
+    M::Error: 'static,
+{
+    /// `initial_supply` is the supply after the block before `start_block`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        start_block: u64,
+        end_block: u64,
+        batch_size: u64,
+        provider: M,
+        max_workers: usize,
+        exporter: EthSupplyCsvExporter,
+        chain_config: ChainConfig,
+        initial_supply: U256,
+    ) -> Self {
+        Self {
+            start_block,
+            end_block,
+            batch_size,
+            provider,
+            max_workers,
+            exporter,
+            chain_config,
+            initial_supply,
+        }
+    }
+
+    /// Unlike the other jobs, the rows depend on every block before them. Blocks are fetched
+    /// concurrently one batch at a time and written in order once the whole batch is known, so
+    /// the last row of the output is always a valid checkpoint.
+    #[instrument(skip_all, fields(start_block = %self.start_block, end_block = %self.end_block))]
+    pub async fn run(&self) -> Result<()> {
+        let start_time = Instant::now();
+        let block_receipts_supported = AtomicBool::new(true);
+
+        let mut progress = ProgressTracker::new("export_eth_supply", Some(self.end_block - self.start_block + 1));
+        progress.start();
+
+        let mut supply = self.initial_supply;
+        let mut batch_start = self.start_block;
+        while batch_start <= self.end_block {
+            let batch_end = self.end_block.min(batch_start + self.batch_size.max(1) - 1);
+
+            let changes = stream::iter(batch_start..=batch_end)
+                .map(|block_number| {
+                    fetch_block_supply_changes(&self.provider, block_number, &self.chain_config, &block_receipts_supported)
+                })
+                .buffered(self.max_workers.max(1))
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error getting supply changes for blocks {} to {}: {}", batch_start, batch_end, e);
+                    e
+                })?;
+
+            let eth_supply_csv_rows = changes
+                .iter()
+                .map(|changes| apply_supply_changes(&mut supply, changes).map(|eth_supply| eth_supply_to_csv_row(&eth_supply)))
+                .collect::<Result<Vec<Vec<String>>>>()?;
+
+            // A row missing in the middle would make every later supply wrong, so write errors
+            // stop the job instead of being logged
+            self.exporter.export_eth_supply(eth_supply_csv_rows)?;
+
+            progress.track(batch_end - batch_start + 1);
+            batch_start = batch_end + 1;
+        }
+
+        progress.finish();
+
+        let duration = start_time.elapsed();
+        info!(
+            "Finished work. Total blocks processed: {}. Supply at block {}: {} wei. Took: {:?}",
+            self.end_block - self.start_block + 1,
+            self.end_block,
+            supply,
+            duration
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,479 @@
+use crate::abi::{format_token, read_abi_dir, Abi};
+use crate::domain::Log;
+use anyhow::{bail, Context, Result};
+use ethers::abi::{Event, ParamType, RawLog, Token};
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Log columns that precede the event parameters in every decoded table.
+const LOG_COLUMNS: [&str; 6] = [
+    "block_number",
+    "block_hash",
+    "transaction_hash",
+    "transaction_index",
+    "log_index",
+    "address",
+];
+
+/// An ABI parameter as written in the JSON. `ethabi` drops the names of tuple components, which
+/// are needed for the column names.
+#[derive(Deserialize)]
+struct JsonParam {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    components: Vec<JsonParam>,
+}
+
+/// One distinct event definition and the table its logs are written to. Identical definitions
+/// from several ABI files share a table.
+struct EventTable {
+    name: String,
+    event: Event,
+    columns: Vec<String>,
+    /// Whether the event is decoded for any address, because an ABI without an address mapping
+    /// defines it
+    global: bool,
+    addresses: HashSet<String>,
+}
+
+/// Decodes logs with the events of a directory of ABI JSON files.
+///
+/// An ABI applies to every address unless the address mapping lists it, in which case it only
+/// applies to the mapped addresses. Mapped ABIs take precedence over global ones.
+pub struct LogDecoder {
+    tables: Vec<EventTable>,
+    tables_by_topic: HashMap<H256, Vec<usize>>,
+}
+
+impl LogDecoder {
+    /// Loads the events of every ABI in `abi_dir`. `abi_addresses` is an optional CSV with
+    /// `address` and `abi` columns, where `abi` is the file name without the `.json` extension.
+    pub fn load(abi_dir: &Path, abi_addresses: Option<&Path>) -> Result<Self> {
+        let addresses_by_abi = match abi_addresses {
+            Some(path) => read_abi_addresses(path)?,
+            None => HashMap::new(),
+        };
+
+        let abis = read_abi_dir(abi_dir)?;
+
+        let mut tables: Vec<EventTable> = vec![];
+        let mut tables_by_key: HashMap<String, usize> = HashMap::new();
+
+        for abi in &abis {
+            let addresses = addresses_by_abi.get(&abi.name);
+
+            for (event, inputs) in abi_events(abi)? {
+                let key = event_key(&event);
+                let index = *tables_by_key.entry(key).or_insert_with(|| {
+                    tables.push(EventTable {
+                        name: String::new(),
+                        columns: param_columns(&event, &inputs),
+                        event,
+                        global: false,
+                        addresses: HashSet::new(),
+                    });
+                    tables.len() - 1
+                });
+
+                match addresses {
+                    Some(addresses) => tables[index].addresses.extend(addresses.iter().cloned()),
+                    None => tables[index].global = true,
+                }
+            }
+        }
+
+        for abi_name in addresses_by_abi.keys() {
+            if !abis.iter().any(|abi| abi.name == *abi_name) {
+                bail!("The address mapping refers to {}.json, which is not in {}", abi_name, abi_dir.display());
+            }
+        }
+
+        // Events that share a name but not a definition, such as the ERC-20 and ERC-721
+        // `Transfer`, get a suffix derived from their definition
+        let mut name_counts: HashMap<String, usize> = HashMap::new();
+        for table in &tables {
+            *name_counts.entry(table.event.name.clone()).or_default() += 1;
+        }
+        for table in tables.iter_mut() {
+            table.name = if name_counts[&table.event.name] > 1 {
+                let key_hash = keccak256(event_key(&table.event));
+                format!("{}_{}", table.event.name, hex::encode(&key_hash[..4]))
+            } else {
+                table.event.name.clone()
+            };
+        }
+
+        let mut tables_by_topic: HashMap<H256, Vec<usize>> = HashMap::new();
+        for (index, table) in tables.iter().enumerate() {
+            tables_by_topic.entry(table.event.signature()).or_default().push(index);
+        }
+
+        Ok(LogDecoder {
+            tables,
+            tables_by_topic,
+        })
+    }
+
+    pub fn events_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn table_name(&self, table: usize) -> &str {
+        &self.tables[table].name
+    }
+
+    pub fn table_header(&self, table: usize) -> Vec<String> {
+        LOG_COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .chain(self.tables[table].columns.iter().cloned())
+            .collect()
+    }
+
+    /// Decodes a log into a row of the table of its event. Returns `None` when no known event
+    /// matches its address, topics and data.
+    pub fn decode(&self, log: &Log) -> Option<(usize, Vec<String>)> {
+        let topics = log
+            .topics
+            .iter()
+            .map(|topic| topic.parse::<H256>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let data = hex::decode(log.data.trim_start_matches("0x")).ok()?;
+        let address = log.address.to_lowercase();
+
+        let candidates = self.tables_by_topic.get(topics.first()?)?;
+        let scoped = candidates
+            .iter()
+            .filter(|index| self.tables[**index].addresses.contains(&address));
+        let global = candidates.iter().filter(|index| self.tables[**index].global);
+
+        for index in scoped.chain(global) {
+            let raw_log = RawLog {
+                topics: topics.clone(),
+                data: data.clone(),
+            };
+            let Ok(decoded) = self.tables[*index].event.parse_log(raw_log) else {
+                continue;
+            };
+
+            let mut row = vec![
+                log.block_number.map(|num| num.to_string()).unwrap_or_default(),
+                log.block_hash.clone().unwrap_or_default(),
+                log.transaction_hash.clone().unwrap_or_default(),
+                log.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
+                log.log_index.map(|num| num.to_string()).unwrap_or_default(),
+                log.address.clone(),
+            ];
+            let inputs = &self.tables[*index].event.inputs;
+            for (input, param) in inputs.iter().zip(decoded.params) {
+                param_values(&input.kind, input.indexed, param.value, &mut row);
+            }
+            return Some((*index, row));
+        }
+
+        None
+    }
+}
+
+fn read_abi_addresses(path: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to read ABI addresses from {}", path.display()))?;
+
+    let mut addresses_by_abi: HashMap<String, Vec<String>> = HashMap::new();
+    for record in reader.deserialize::<HashMap<String, String>>() {
+        let record = record?;
+        let (Some(address), Some(abi)) = (record.get("address"), record.get("abi")) else {
+            bail!("{} needs address and abi columns", path.display());
+        };
+        addresses_by_abi
+            .entry(abi.trim().to_string())
+            .or_default()
+            .push(address.trim().to_lowercase());
+    }
+
+    Ok(addresses_by_abi)
+}
+
+/// The non-anonymous events of an ABI, with their inputs as written in the JSON.
+fn abi_events(abi: &Abi) -> Result<Vec<(Event, Vec<JsonParam>)>> {
+    let mut events = vec![];
+    for item in &abi.items {
+        if item.get("type").and_then(|kind| kind.as_str()) != Some("event") {
+            continue;
+        }
+        let inputs = item.get("inputs").cloned().unwrap_or_default();
+        let event: Event = serde_json::from_value(item.clone())
+            .with_context(|| format!("Invalid event in {}.json", abi.name))?;
+        if event.anonymous {
+            continue;
+        }
+        let inputs: Vec<JsonParam> = serde_json::from_value(inputs).unwrap_or_default();
+        events.push((event, inputs));
+    }
+
+    Ok(events)
+}
+
+/// Identifies an event definition: the signature plus which parameters are indexed, since that
+/// changes how a log is decoded.
+fn event_key(event: &Event) -> String {
+    let params = event
+        .inputs
+        .iter()
+        .map(|input| format!("{}{}", input.kind, if input.indexed { " indexed" } else { "" }))
+        .collect::<Vec<_>>();
+    format!("{}({})", event.name, params.join(","))
+}
+
+/// One column per parameter, except that non-indexed tuples get a column per component.
+/// Unnamed parameters are called `arg{position}`.
+fn param_columns(event: &Event, inputs: &[JsonParam]) -> Vec<String> {
+    let mut columns = vec![];
+    for (position, input) in event.inputs.iter().enumerate() {
+        let name = match input.name.as_str() {
+            "" => format!("arg{}", position),
+            name if LOG_COLUMNS.contains(&name) => format!("event_{}", name),
+            name => name.to_string(),
+        };
+        let components = inputs.get(position).map(|param| param.components.as_slice()).unwrap_or_default();
+        if input.indexed {
+            columns.push(name);
+        } else {
+            tuple_columns(&name, &input.kind, components, &mut columns);
+        }
+    }
+    columns
+}
+
+fn tuple_columns(name: &str, kind: &ParamType, components: &[JsonParam], columns: &mut Vec<String>) {
+    let ParamType::Tuple(kinds) = kind else {
+        columns.push(name.to_string());
+        return;
+    };
+
+    for (position, kind) in kinds.iter().enumerate() {
+        let component = components.get(position);
+        let component_name = match component.map(|component| component.name.as_str()) {
+            Some("") | None => position.to_string(),
+            Some(component_name) => component_name.to_string(),
+        };
+        let nested = component.map(|component| component.components.as_slice()).unwrap_or_default();
+        tuple_columns(&format!("{}_{}", name, component_name), kind, nested, columns);
+    }
+}
+
+/// Renders a decoded parameter the same way [`param_columns`] lays out its columns. Indexed
+/// dynamic values are only available as the keccak hash stored in the topic.
+fn param_values(kind: &ParamType, indexed: bool, value: Token, row: &mut Vec<String>) {
+    match (kind, value) {
+        (ParamType::Tuple(kinds), Token::Tuple(values)) if !indexed => {
+            for (kind, value) in kinds.iter().zip(values) {
+                param_values(kind, false, value, row);
+            }
+        }
+        (_, value) => row.push(format_token(&value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::encode;
+    use ethers::types::{Address, U256};
+    use tempfile::TempDir;
+
+    const ERC20_ABI: &str = r#"[{"type":"event","name":"Transfer","anonymous":false,"inputs":[
+        {"name":"from","type":"address","indexed":true},
+        {"name":"to","type":"address","indexed":true},
+        {"name":"value","type":"uint256","indexed":false}]}]"#;
+    const ERC721_ABI: &str = r#"[{"type":"event","name":"Transfer","anonymous":false,"inputs":[
+        {"name":"from","type":"address","indexed":true},
+        {"name":"to","type":"address","indexed":true},
+        {"name":"tokenId","type":"uint256","indexed":true}]}]"#;
+    const ORDER_ABI: &str = r#"[{"type":"event","name":"OrderFilled","anonymous":false,"inputs":[
+        {"name":"address","type":"address","indexed":true},
+        {"name":"","type":"uint256","indexed":false},
+        {"name":"order","type":"tuple","indexed":false,"components":[
+            {"name":"maker","type":"address"},
+            {"name":"","type":"uint256"},
+            {"name":"fee","type":"tuple","components":[
+                {"name":"recipient","type":"address"},
+                {"name":"amount","type":"uint256"}]}]}]}]"#;
+
+    const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const BAYC: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+
+    /// A directory with one `<name>.json` file per ABI, deleted when dropped.
+    fn abi_dir(abis: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (abi_name, abi) in abis {
+            std::fs::write(dir.path().join(format!("{}.json", abi_name)), abi).unwrap();
+        }
+        dir
+    }
+
+    fn address_topic(address: &str) -> String {
+        format!("0x000000000000000000000000{}", address.trim_start_matches("0x"))
+    }
+
+    fn log(address: &str, topics: Vec<String>, data: &str) -> Log {
+        Log {
+            log_index: Some(12),
+            transaction_hash: Some("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060".to_string()),
+            transaction_index: Some(3),
+            block_hash: Some("0x2f1d4d4b2bd5e8d1cd02e3c7bb5e8c37e1d4c5f3e0bcfa8b63f7bbf8b6e03d6a".to_string()),
+            block_number: Some(17_000_000),
+            address: address.to_string(),
+            data: data.to_string(),
+            topics,
+        }
+    }
+
+    /// 1,500 USDC from Binance 14 to another account.
+    fn usdc_transfer() -> Log {
+        log(
+            USDC,
+            vec![
+                TRANSFER_TOPIC.to_string(),
+                address_topic("0x28c6c06298d514db089934071355e5743bf21d60"),
+                address_topic("0x21a31ee1afc51d94c2efccaa2092ad1028285549"),
+            ],
+            "0x0000000000000000000000000000000000000000000000000000000059682f00",
+        )
+    }
+
+    /// The mint of Bored Ape #0.
+    fn bayc_mint() -> Log {
+        log(
+            BAYC,
+            vec![
+                TRANSFER_TOPIC.to_string(),
+                address_topic("0x0000000000000000000000000000000000000000"),
+                address_topic("0xaba7161a7fb69c88e16ed9f455ce62b791ee4d03"),
+                format!("0x{:064x}", 0),
+            ],
+            "0x",
+        )
+    }
+
+    #[test]
+    fn suffixes_events_that_share_a_name() {
+        let dir = abi_dir(&[("erc20", ERC20_ABI), ("erc721", ERC721_ABI)]);
+        let decoder = LogDecoder::load(dir.path(), None).unwrap();
+
+        assert_eq!(decoder.events_count(), 2);
+        // keccak256 of "Transfer(address indexed,address indexed,uint256)" and of the same with
+        // an indexed `tokenId`
+        assert_eq!(decoder.table_name(0), "Transfer_7cc808e2");
+        assert_eq!(decoder.table_name(1), "Transfer_1ff5c586");
+    }
+
+    #[test]
+    fn keeps_the_event_name_without_a_collision() {
+        let dir = abi_dir(&[("erc20", ERC20_ABI), ("usdc", ERC20_ABI)]);
+        let decoder = LogDecoder::load(dir.path(), None).unwrap();
+
+        assert_eq!(decoder.events_count(), 1);
+        assert_eq!(decoder.table_name(0), "Transfer");
+    }
+
+    #[test]
+    fn decodes_erc20_and_erc721_transfers_by_their_indexed_parameters() {
+        let dir = abi_dir(&[("erc20", ERC20_ABI), ("erc721", ERC721_ABI)]);
+        let decoder = LogDecoder::load(dir.path(), None).unwrap();
+
+        let (table, row) = decoder.decode(&usdc_transfer()).unwrap();
+        assert_eq!(table, 0);
+        assert_eq!(
+            decoder.table_header(table),
+            [
+                "block_number",
+                "block_hash",
+                "transaction_hash",
+                "transaction_index",
+                "log_index",
+                "address",
+                "from",
+                "to",
+                "value"
+            ]
+        );
+        assert_eq!(row[0], "17000000");
+        assert_eq!(row[3..], ["3", "12", USDC, "0x28c6c06298d514db089934071355e5743bf21d60", "0x21a31ee1afc51d94c2efccaa2092ad1028285549", "1500000000"]);
+
+        let (table, row) = decoder.decode(&bayc_mint()).unwrap();
+        assert_eq!(table, 1);
+        assert_eq!(decoder.table_header(table)[6..], ["from", "to", "tokenId"]);
+        assert_eq!(row[6..], ["0x0000000000000000000000000000000000000000", "0xaba7161a7fb69c88e16ed9f455ce62b791ee4d03", "0"]);
+    }
+
+    #[test]
+    fn decodes_nothing_for_unknown_or_malformed_logs() {
+        let dir = abi_dir(&[("erc20", ERC20_ABI)]);
+        let decoder = LogDecoder::load(dir.path(), None).unwrap();
+
+        let mut approval = usdc_transfer();
+        approval.topics[0] = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925".to_string();
+        assert!(decoder.decode(&approval).is_none());
+
+        // An ERC-721 transfer has no data word for the ERC-20 `value`
+        assert!(decoder.decode(&bayc_mint()).is_none());
+    }
+
+    #[test]
+    fn applies_mapped_abis_only_to_their_addresses() {
+        let dir = abi_dir(&[("erc20", ERC20_ABI)]);
+        let abi_addresses = dir.path().join("abi_addresses.csv");
+        std::fs::write(&abi_addresses, format!("address,abi\n{},erc20\n", USDC.to_uppercase().replacen("0X", "0x", 1))).unwrap();
+        let decoder = LogDecoder::load(dir.path(), Some(&abi_addresses)).unwrap();
+
+        assert!(decoder.decode(&usdc_transfer()).is_some());
+        let mut other_token = usdc_transfer();
+        other_token.address = "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string();
+        assert!(decoder.decode(&other_token).is_none());
+    }
+
+    #[test]
+    fn lays_out_tuples_as_one_column_per_component() {
+        let abi = Abi {
+            name: "order".to_string(),
+            items: serde_json::from_str(ORDER_ABI).unwrap(),
+        };
+        let (event, inputs) = abi_events(&abi).unwrap().remove(0);
+
+        assert_eq!(
+            param_columns(&event, &inputs),
+            ["event_address", "arg1", "order_maker", "order_1", "order_fee_recipient", "order_fee_amount"]
+        );
+    }
+
+    #[test]
+    fn decodes_tuples_into_their_component_columns() {
+        let dir = abi_dir(&[("order", ORDER_ABI)]);
+        let decoder = LogDecoder::load(dir.path(), None).unwrap();
+        let maker: Address = "0x28c6c06298d514db089934071355e5743bf21d60".parse().unwrap();
+        let recipient: Address = "0x21a31ee1afc51d94c2efccaa2092ad1028285549".parse().unwrap();
+        let data = encode(&[
+            Token::Uint(U256::from(7)),
+            Token::Tuple(vec![
+                Token::Address(maker),
+                Token::Uint(U256::from(1_000)),
+                Token::Tuple(vec![Token::Address(recipient), Token::Uint(U256::from(25))]),
+            ]),
+        ]);
+        let signature = format!("{:#x}", decoder.tables[0].event.signature());
+        let order_filled = log(USDC, vec![signature, address_topic(USDC)], &format!("0x{}", hex::encode(data)));
+
+        let (_, row) = decoder.decode(&order_filled).unwrap();
+        assert_eq!(
+            row[6..],
+            [USDC, "7", "0x28c6c06298d514db089934071355e5743bf21d60", "1000", "0x21a31ee1afc51d94c2efccaa2092ad1028285549", "25"]
+        );
+    }
+}
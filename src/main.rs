@@ -1,16 +1,49 @@
+mod abi;
+mod bytecode;
+mod cache;
+mod calldata_decoder;
+mod chain_config;
 mod domain;
+mod exporters;
+mod extractors;
+mod jobs;
+mod log_decoder;
 mod mappers;
 mod providers;
-mod jobs;
-mod exporters;
+mod proxies;
+mod requests;
+mod rewards;
+mod signatures;
+mod state_diffs;
+mod supply;
+mod tokens;
+mod traces;
 mod utils;
 
-use clap::Parser;
-use jobs::ExportBlocksJob;
+use cache::DiskCache;
+use calldata_decoder::CalldataDecoder;
+use chain_config::ChainConfig;
+use exporters::{
+    AccountStateCsvExporter, ContractsCsvExporter, CsvExporter, DecodedLogsCsvExporter,
+    EthSupplyCsvExporter, ReceiptsCsvExporter, RequestsCsvExporter, StateDiffsCsvExporter,
+    TokenBalancesCsvExporter, TokenTransfersCsvExporter, TokensCsvExporter, TracesCsvExporter,
+};
+use jobs::{
+    DecodeLogsFromFileJob, DecodeLogsJob, ExportAccountStateJob, ExportBlocksJob,
+    ExportContractsJob, ExportEthSupplyJob, ExportReceiptsJob, ExportRequestsJob,
+    ExportStateDiffsJob, ExportTokenBalancesJob, ExportTokensJob, ExportTracesJob,
+    ExtractTokenTransfersFromLogsJob, ExtractTokenTransfersJob,
+};
+use log_decoder::LogDecoder;
 use providers::get_provider_from_uri;
-use exporters::CsvExporter;
+use supply::read_supply_checkpoint;
+use tokens::TokenService;
+use traces::TraceSource;
+use utils::{read_address_pairs, read_addresses};
+use clap::Parser;
+use ethers::providers::Middleware;
+use ethers::types::{Address, U256};
 use std::path::PathBuf;
-use tokio;
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser, Debug)]
@@ -29,6 +62,28 @@ struct Opts {
 enum SubCommand {
     #[clap(name = "export_blocks_and_transactions", about = "Export blocks and transactions", alias = "export-blocks-and-transactions")]
     ExportBlocksAndTransactions(ExportOpts),
+    #[clap(name = "export_receipts_and_logs", about = "Export receipts and logs", alias = "export-receipts-and-logs")]
+    ExportReceiptsAndLogs(ExportReceiptsOpts),
+    #[clap(name = "extract_token_transfers", about = "Extract ERC-20, ERC-721 and ERC-1155 token transfers from a node or a logs file", alias = "extract-token-transfers")]
+    ExtractTokenTransfers(ExtractTokenTransfersOpts),
+    #[clap(name = "export_contracts", about = "Export contracts deployed in a block range", alias = "export-contracts")]
+    ExportContracts(ExportContractsOpts),
+    #[clap(name = "export_tokens", about = "Export token metadata for a list of token addresses", alias = "export-tokens")]
+    ExportTokens(ExportTokensOpts),
+    #[clap(name = "export_traces", about = "Export traces with trace_block or debug_traceBlockByNumber", alias = "export-traces")]
+    ExportTraces(ExportTracesOpts),
+    #[clap(name = "export_state_diffs", about = "Export per-transaction balance and storage changes", alias = "export-state-diffs")]
+    ExportStateDiffs(ExportStateDiffsOpts),
+    #[clap(name = "export_account_state", about = "Export balance, nonce and code hash snapshots for a list of addresses", alias = "export-account-state")]
+    ExportAccountState(ExportAccountStateOpts),
+    #[clap(name = "export_token_balances", about = "Export ERC-20 balanceOf snapshots for holder and token addresses", alias = "export-token-balances")]
+    ExportTokenBalances(ExportTokenBalancesOpts),
+    #[clap(name = "export_requests", about = "Export EIP-7685 deposit, withdrawal and consolidation requests", alias = "export-requests")]
+    ExportRequests(ExportRequestsOpts),
+    #[clap(name = "decode_logs", about = "Decode logs into per-event tables with a directory of ABI files", alias = "decode-logs")]
+    DecodeLogs(DecodeLogsOpts),
+    #[clap(name = "export_eth_supply", about = "Export the ETH supply after every block with its issuance, burn and withdrawals", alias = "export-eth-supply")]
+    ExportEthSupply(ExportEthSupplyOpts),
 }
 
 #[derive(Parser, Debug)]
@@ -53,6 +108,327 @@ struct ExportOpts {
 
     #[clap(long, help = "Output directory for transactions")]
     transactions_output: Option<PathBuf>,
+
+    #[clap(long, help = "Fetch receipts and add receipt_* columns to transactions")]
+    with_receipts: bool,
+
+    #[clap(long, help = "Recover each sender from its signature and flag mismatches with from_address")]
+    verify_signatures: bool,
+
+    #[clap(long, help = "Output file for EIP-4895 withdrawals")]
+    withdrawals_output: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for uncle (ommer) blocks")]
+    uncles_output: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for EIP-2930 access lists, one row per address and storage key")]
+    transaction_access_list_output: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for EIP-7702 authorizations with their recovered authority")]
+    authorizations_output: Option<PathBuf>,
+
+    #[clap(long, help = "Directory of ABI JSON files used to decode transaction inputs")]
+    abi_dir: Option<PathBuf>,
+
+    #[clap(long, help = "4byte-style signature file used to decode transaction inputs")]
+    signatures_db: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for block and uncle rewards, priority fees and burnt fees; fetches receipts but only adds them to transactions with --with-receipts")]
+    block_rewards_output: Option<PathBuf>,
+
+    #[clap(long, help = "JSON file with the fork blocks of the chain; defaults to mainnet")]
+    chain_config: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportReceiptsOpts {
+    #[clap(long, help = "Start block")]
+    start_block: u64,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for receipts")]
+    receipts_output: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for logs")]
+    logs_output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExtractTokenTransfersOpts {
+    #[clap(long, help = "Logs CSV produced by export_receipts_and_logs; reads from the node when omitted", conflicts_with = "provider_uri")]
+    logs: Option<PathBuf>,
+
+    #[clap(long, help = "Start block", required_unless_present = "logs")]
+    start_block: Option<u64>,
+
+    #[clap(long, help = "End block", required_unless_present = "logs")]
+    end_block: Option<u64>,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI", required_unless_present = "logs")]
+    provider_uri: Option<String>,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for token transfers")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportContractsOpts {
+    #[clap(long, help = "Start block")]
+    start_block: u64,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for contracts")]
+    output: Option<PathBuf>,
+
+    #[clap(long, help = "Find contracts through trace_block, including those created by other contracts")]
+    include_traces: bool,
+
+    #[clap(long, help = "Output file for proxy Upgraded and BeaconUpgraded events")]
+    proxy_upgrades_output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportTokensOpts {
+    #[clap(long, help = "File with one token address per line, or a CSV with an address column")]
+    token_addresses: PathBuf,
+
+    #[clap(long, help = "Block to read the token metadata at; defaults to the latest block")]
+    block_number: Option<u64>,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for tokens")]
+    output: Option<PathBuf>,
+
+    #[clap(long, help = "JSON file that caches token metadata between runs")]
+    cache_file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportTracesOpts {
+    #[clap(long, help = "Start block")]
+    start_block: u64,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for traces")]
+    output: Option<PathBuf>,
+
+    #[clap(long, value_enum, default_value = "parity", help = "Trace source: parity (trace_block) or geth (debug_traceBlockByNumber with callTracer)")]
+    trace_source: TraceSource,
+}
+
+#[derive(Parser, Debug)]
+struct ExportStateDiffsOpts {
+    #[clap(long, help = "Start block")]
+    start_block: u64,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for balance, nonce and code changes")]
+    balance_changes_output: Option<PathBuf>,
+
+    #[clap(long, help = "Output file for storage changes")]
+    storage_changes_output: Option<PathBuf>,
+
+    #[clap(long, value_enum, default_value = "parity", help = "State diff source: parity (trace_replayBlockTransactions) or geth (prestateTracer in diff mode)")]
+    state_diff_source: TraceSource,
+}
+
+#[derive(Parser, Debug)]
+struct ExportAccountStateOpts {
+    #[clap(long, help = "File with one address per line, or a CSV with an address column")]
+    addresses: PathBuf,
+
+    #[clap(long, alias = "block-number", help = "Start block, or the only block when --end-block is omitted")]
+    start_block: u64,
+
+    #[clap(long, help = "End block; defaults to the start block")]
+    end_block: Option<u64>,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for account state")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportTokenBalancesOpts {
+    #[clap(long, conflicts_with_all = ["holders", "tokens"], required_unless_present_all = ["holders", "tokens"], help = "CSV with holder_address and token_address columns")]
+    pairs: Option<PathBuf>,
+
+    #[clap(long, requires = "tokens", help = "File with holder addresses; every holder is paired with every token")]
+    holders: Option<PathBuf>,
+
+    #[clap(long, requires = "holders", help = "File with token addresses; every token is paired with every holder")]
+    tokens: Option<PathBuf>,
+
+    #[clap(long, help = "Block to read the balances at; defaults to the latest block")]
+    block_number: Option<u64>,
+
+    #[clap(long, default_value = "100", help = "Number of balanceOf calls aggregated into one Multicall3 call")]
+    calls_per_request: usize,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for token balances")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportRequestsOpts {
+    #[clap(long, help = "Start block")]
+    start_block: u64,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for requests")]
+    output: Option<PathBuf>,
+
+    #[clap(long, default_value = requests::MAINNET_DEPOSIT_CONTRACT_ADDRESS, help = "Deposit contract address of the chain")]
+    deposit_contract_address: Address,
+}
+
+#[derive(Parser, Debug)]
+struct DecodeLogsOpts {
+    #[clap(long, help = "Directory of ABI JSON files or compiler artifacts with an abi field")]
+    abi_dir: PathBuf,
+
+    #[clap(long, help = "CSV with address and abi columns restricting ABI files to addresses; abi is the file name without .json")]
+    abi_addresses: Option<PathBuf>,
+
+    #[clap(long, help = "Logs CSV produced by export_receipts_and_logs; reads from the node when omitted", conflicts_with = "provider_uri")]
+    logs: Option<PathBuf>,
+
+    #[clap(long, help = "Start block", required_unless_present = "logs")]
+    start_block: Option<u64>,
+
+    #[clap(long, help = "End block", required_unless_present = "logs")]
+    end_block: Option<u64>,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI", required_unless_present = "logs")]
+    provider_uri: Option<String>,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Directory for the per-event tables")]
+    output_dir: PathBuf,
+
+    #[clap(long, help = "Output file for logs that match no event")]
+    residual_output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportEthSupplyOpts {
+    #[clap(long, help = "Start block; with --resume, only used when the output has no rows yet")]
+    start_block: Option<u64>,
+
+    #[clap(long, help = "End block")]
+    end_block: u64,
+
+    #[clap(long, default_value = "100", help = "Batch size")]
+    batch_size: u64,
+
+    #[clap(long, help = "Provider URI")]
+    provider_uri: String,
+
+    #[clap(long, default_value = "5", help = "Max workers")]
+    max_workers: usize,
+
+    #[clap(long, help = "Output file for the supply")]
+    output: PathBuf,
+
+    #[clap(long, help = "Continue after the last row of the output file and append to it")]
+    resume: bool,
+
+    #[clap(long, help = "Supply in wei after the block before --start-block; defaults to the genesis supply when starting at block 0")]
+    initial_supply: Option<String>,
+
+    #[clap(long, help = "JSON file with the fork blocks and genesis supply of the chain; defaults to mainnet")]
+    chain_config: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -65,11 +441,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match opts.subcmd {
         SubCommand::ExportBlocksAndTransactions(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
             let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
             let exporter = CsvExporter::new(
                 export_opts.blocks_output,
                 export_opts.transactions_output,
+                export_opts.withdrawals_output,
+                export_opts.uncles_output,
+                export_opts.transaction_access_list_output,
+                export_opts.authorizations_output,
+                export_opts.block_rewards_output,
             )?;
+            let calldata_decoder = if export_opts.abi_dir.is_some() || export_opts.signatures_db.is_some() {
+                let decoder = CalldataDecoder::load(export_opts.abi_dir.as_deref(), export_opts.signatures_db.as_deref())?;
+                tracing::info!("Loaded {} function selectors", decoder.selectors_count());
+                Some(decoder)
+            } else {
+                None
+            };
             let job = ExportBlocksJob::new(
                 export_opts.start_block,
                 export_opts.end_block,
@@ -77,10 +466,270 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 provider,
                 export_opts.max_workers,
                 exporter,
+                export_opts.with_receipts,
+                export_opts.verify_signatures,
+                calldata_decoder,
+                ChainConfig::load(export_opts.chain_config.as_deref())?,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportReceiptsAndLogs(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let exporter = ReceiptsCsvExporter::new(
+                export_opts.receipts_output,
+                export_opts.logs_output,
+            )?;
+            let job = ExportReceiptsJob::new(
+                export_opts.start_block,
+                export_opts.end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExtractTokenTransfers(extract_opts) => {
+            let exporter = TokenTransfersCsvExporter::new(extract_opts.output)?;
+            match extract_opts.logs {
+                Some(logs) => {
+                    let job = ExtractTokenTransfersFromLogsJob::new(logs, extract_opts.batch_size, exporter);
+                    job.run()?;
+                }
+                None => {
+                    // clap guarantees these are present when --logs is not given
+                    let (start_block, end_block) = (extract_opts.start_block.unwrap(), extract_opts.end_block.unwrap());
+                    check_block_range(start_block, end_block)?;
+                    let provider = get_provider_from_uri(&extract_opts.provider_uri.unwrap()).await?;
+                    let job = ExtractTokenTransfersJob::new(
+                        start_block,
+                        end_block,
+                        extract_opts.batch_size,
+                        provider,
+                        extract_opts.max_workers,
+                        exporter,
+                    );
+                    job.run().await?;
+                }
+            }
+        }
+        SubCommand::ExportContracts(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let exporter = ContractsCsvExporter::new(export_opts.output, export_opts.proxy_upgrades_output)?;
+            let job = ExportContractsJob::new(
+                export_opts.start_block,
+                export_opts.end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+                export_opts.include_traces,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportTokens(export_opts) => {
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let block_number = match export_opts.block_number {
+                Some(block_number) => block_number,
+                None => provider.get_block_number().await?.as_u64(),
+            };
+            let token_addresses = read_addresses(&export_opts.token_addresses)?;
+            let token_service = TokenService::new(provider, DiskCache::load(export_opts.cache_file)?);
+            let exporter = TokensCsvExporter::new(export_opts.output)?;
+            let job = ExportTokensJob::new(
+                token_addresses,
+                block_number,
+                export_opts.batch_size,
+                export_opts.max_workers,
+                token_service,
+                exporter,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportTraces(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let exporter = TracesCsvExporter::new(export_opts.output)?;
+            let job = ExportTracesJob::new(
+                export_opts.start_block,
+                export_opts.end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+                export_opts.trace_source,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportStateDiffs(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let exporter = StateDiffsCsvExporter::new(
+                export_opts.balance_changes_output,
+                export_opts.storage_changes_output,
+            )?;
+            let job = ExportStateDiffsJob::new(
+                export_opts.start_block,
+                export_opts.end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+                export_opts.state_diff_source,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportAccountState(export_opts) => {
+            let end_block = export_opts.end_block.unwrap_or(export_opts.start_block);
+            check_block_range(export_opts.start_block, end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let addresses = read_addresses(&export_opts.addresses)?;
+            let exporter = AccountStateCsvExporter::new(export_opts.output)?;
+            let job = ExportAccountStateJob::new(
+                addresses,
+                export_opts.start_block,
+                end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportTokenBalances(export_opts) => {
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let block_number = match export_opts.block_number {
+                Some(block_number) => block_number,
+                None => provider.get_block_number().await?.as_u64(),
+            };
+            let pairs = match (&export_opts.pairs, &export_opts.holders, &export_opts.tokens) {
+                (Some(pairs), _, _) => read_address_pairs(pairs)?,
+                (None, Some(holders), Some(tokens)) => {
+                    let holders = read_addresses(holders)?;
+                    let tokens = read_addresses(tokens)?;
+                    holders
+                        .iter()
+                        .flat_map(|holder| tokens.iter().map(move |token| (*holder, *token)))
+                        .collect()
+                }
+                _ => unreachable!("clap requires --pairs or both --holders and --tokens"),
+            };
+            let exporter = TokenBalancesCsvExporter::new(export_opts.output)?;
+            let job = ExportTokenBalancesJob::new(
+                pairs,
+                block_number,
+                export_opts.calls_per_request,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+            );
+            job.run().await?;
+        }
+        SubCommand::ExportRequests(export_opts) => {
+            check_block_range(export_opts.start_block, export_opts.end_block)?;
+            let provider = get_provider_from_uri(&export_opts.provider_uri).await?;
+            let exporter = RequestsCsvExporter::new(export_opts.output)?;
+            let job = ExportRequestsJob::new(
+                export_opts.start_block,
+                export_opts.end_block,
+                export_opts.batch_size,
+                provider,
+                export_opts.max_workers,
+                exporter,
+                export_opts.deposit_contract_address,
+            );
+            job.run().await?;
+        }
+        SubCommand::DecodeLogs(decode_opts) => {
+            let decoder = LogDecoder::load(&decode_opts.abi_dir, decode_opts.abi_addresses.as_deref())?;
+            tracing::info!("Loaded {} events from {}", decoder.events_count(), decode_opts.abi_dir.display());
+            let exporter = DecodedLogsCsvExporter::new(decode_opts.output_dir, decode_opts.residual_output)?;
+            match decode_opts.logs {
+                Some(logs) => {
+                    let job = DecodeLogsFromFileJob::new(logs, decode_opts.batch_size, decoder, exporter);
+                    job.run()?;
+                }
+                None => {
+                    // clap guarantees these are present when --logs is not given
+                    let (start_block, end_block) = (decode_opts.start_block.unwrap(), decode_opts.end_block.unwrap());
+                    check_block_range(start_block, end_block)?;
+                    let provider = get_provider_from_uri(&decode_opts.provider_uri.unwrap()).await?;
+                    let job = DecodeLogsJob::new(
+                        start_block,
+                        end_block,
+                        decode_opts.batch_size,
+                        provider,
+                        decode_opts.max_workers,
+                        decoder,
+                        exporter,
+                    );
+                    job.run().await?;
+                }
+            }
+        }
+        SubCommand::ExportEthSupply(supply_opts) => {
+            let chain_config = ChainConfig::load(supply_opts.chain_config.as_deref())?;
+            let checkpoint = if supply_opts.resume {
+                read_supply_checkpoint(&supply_opts.output)?
+            } else {
+                None
+            };
+
+            let (start_block, initial_supply) = match checkpoint {
+                Some((block_number, supply)) => {
+                    tracing::info!("Resuming after block {} with a supply of {} wei", block_number, supply);
+                    (block_number + 1, supply)
+                }
+                None => {
+                    let start_block = supply_opts
+                        .start_block
+                        .ok_or("--start-block is required unless --resume finds a previous row")?;
+                    check_block_range(start_block, supply_opts.end_block)?;
+                    let initial_supply = match (&supply_opts.initial_supply, start_block) {
+                        (Some(initial_supply), _) => initial_supply.as_str(),
+                        (None, 0) => chain_config
+                            .genesis_supply
+                            .as_deref()
+                            .ok_or("The chain config has no genesisSupply; pass --initial-supply")?,
+                        (None, _) => return Err("--initial-supply is required when not starting at block 0".into()),
+                    };
+                    let initial_supply = U256::from_dec_str(initial_supply)
+                        .map_err(|_| format!("Invalid supply {}; expected a decimal amount in wei", initial_supply))?;
+                    (start_block, initial_supply)
+                }
+            };
+
+            if start_block > supply_opts.end_block {
+                tracing::info!("The supply is already known up to block {}", start_block - 1);
+                return Ok(());
+            }
+
+            let provider = get_provider_from_uri(&supply_opts.provider_uri).await?;
+            let exporter = EthSupplyCsvExporter::new(supply_opts.output, checkpoint.is_some())?;
+            let job = ExportEthSupplyJob::new(
+                start_block,
+                supply_opts.end_block,
+                supply_opts.batch_size,
+                provider,
+                supply_opts.max_workers,
+                exporter,
+                chain_config,
+                initial_supply,
             );
             job.run().await?;
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Rejects a block range that ends before it starts, which the jobs cannot walk.
+fn check_block_range(start_block: u64, end_block: u64) -> Result<(), String> {
+    if start_block > end_block {
+        return Err(format!("--end-block {} is before --start-block {}", end_block, start_block));
+    }
+    Ok(())
+}
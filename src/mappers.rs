@@ -1,11 +1,61 @@
-use crate::domain::{Block, Transaction};
-use chrono::{DateTime, NaiveDateTime, Utc};
-use ethers::types::{Block as EthersBlock, Transaction as EthersTransaction, U64, U256};
-use serde_json::Value;
+use crate::bytecode::{
+    get_function_sighashes, is_erc20_contract, is_erc721_contract, parse_compiler_metadata,
+};
+use crate::domain::{
+    AccessListItem, AccountState, Authorization, BalanceChange, Block, BlockReward, Contract,
+    EthSupply, ExecutionRequest, Log, ProxyUpgrade, Receipt, StorageChange, Token, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransactionAccessListEntry, TransactionAuthorization, Uncle,
+    Withdrawal,
+};
+use crate::signatures::{authorization_list, legacy_chain_id, SignedAuthorization};
+use ethers::types::{
+    Action, Address, Block as EthersBlock, Bytes, CallType, Log as EthersLog, OtherFields, Res,
+    RewardType, SignatureError, Trace as EthersTrace, Transaction as EthersTransaction,
+    TransactionReceipt as EthersTransactionReceipt, H256, U256,
+};
 
 pub fn ethers_block_to_block(ethers_block: EthersBlock<EthersTransaction>) -> Option<Block> {
-    let timestamp = ethers_block.timestamp.as_u64();
+    let mut block = ethers_block_header_to_block(&ethers_block)?;
+    let timestamp = block.timestamp;
 
+    block.transactions = ethers_block
+        .transactions
+        .into_iter()
+        .map(|tx| ethers_transaction_to_transaction(tx, timestamp))
+        .collect();
+    block.withdrawals = ethers_block
+        .withdrawals
+        .unwrap_or_default()
+        .into_iter()
+        .map(|withdrawal| Withdrawal {
+            index: withdrawal.index.as_u64(),
+            validator_index: withdrawal.validator_index.as_u64(),
+            address: format!("{:#x}", withdrawal.address),
+            amount: withdrawal.amount.as_u64(),
+            block_number: block.number,
+            block_hash: block.hash.clone(),
+        })
+        .collect();
+
+    Some(block)
+}
+
+/// Maps an uncle header returned by `eth_getUncleByBlockHashAndIndex`.
+pub fn ethers_uncle_to_uncle(
+    ethers_uncle: &EthersBlock<H256>,
+    including_block: &Block,
+    uncle_index: u64,
+) -> Option<Uncle> {
+    Some(Uncle {
+        block: ethers_block_header_to_block(ethers_uncle)?,
+        including_block_number: including_block.number,
+        including_block_hash: including_block.hash.clone(),
+        uncle_index,
+    })
+}
+
+/// Maps the header fields of a block, leaving its transactions and withdrawals empty.
+fn ethers_block_header_to_block<TX>(ethers_block: &EthersBlock<TX>) -> Option<Block> {
     Some(Block {
         number: ethers_block.number?.as_u64(),
         hash: format!("{:#x}", ethers_block.hash?),
@@ -23,17 +73,18 @@ pub fn ethers_block_to_block(ethers_block: EthersBlock<EthersTransaction>) -> Op
         extra_data: format!("{:#x}", ethers_block.extra_data),
         gas_limit: ethers_block.gas_limit.as_u64(),
         gas_used: ethers_block.gas_used.as_u64(),
-        timestamp,
+        timestamp: ethers_block.timestamp.as_u64(),
         transaction_count: ethers_block.transactions.len() as u64,
         base_fee_per_gas: ethers_block.base_fee_per_gas.map(|v| v.as_u64()),
         withdrawals_root: ethers_block.withdrawals_root.map(|v| format!("{:#x}", v)),
         blob_gas_used: ethers_block.blob_gas_used.map(|v| v.as_u64()),
         excess_blob_gas: ethers_block.excess_blob_gas.map(|v| v.as_u64()),
-        transactions: ethers_block
-            .transactions
-            .into_iter()
-            .map(|tx| ethers_transaction_to_transaction(tx, timestamp))
-            .collect(),
+        parent_beacon_block_root: ethers_block.parent_beacon_block_root.map(|v| format!("{:#x}", v)),
+        requests_hash: other_field_as_h256(&ethers_block.other, "requestsHash").map(|v| format!("{:#x}", v)),
+        mix_hash: ethers_block.mix_hash.map(|v| format!("{:#x}", v)),
+        withdrawals_count: ethers_block.withdrawals.as_ref().map(|withdrawals| withdrawals.len() as u64),
+        transactions: vec![],
+        withdrawals: vec![],
     })
 }
 
@@ -41,6 +92,11 @@ pub fn ethers_transaction_to_transaction(
     ethers_tx: EthersTransaction,
     block_timestamp: u64,
 ) -> Transaction {
+    let v = ethers_tx.v.as_u64();
+    let is_typed = ethers_tx.transaction_type.is_some_and(|transaction_type| !transaction_type.is_zero());
+    let y_parity = other_field_as_u64(&ethers_tx.other, "yParity").or_else(|| is_typed.then_some(v));
+    let authorization_list = authorization_list(&ethers_tx.other);
+    let max_fee_per_blob_gas = other_field_as_u256(&ethers_tx.other, "maxFeePerBlobGas");
     let other_fields = serde_json::to_value(ethers_tx.other).unwrap_or_default();
 
     Transaction {
@@ -56,61 +112,837 @@ pub fn ethers_transaction_to_transaction(
         gas_price: ethers_tx.gas_price.unwrap_or_default().to_string(),
         input: format!("{:#x}", ethers_tx.input),
         block_timestamp,
-        max_fee_per_gas: ethers_tx.max_fee_per_gas.map(|v| v.as_u64()),
-        max_priority_fee_per_gas: ethers_tx.max_priority_fee_per_gas.map(|v| v.as_u64()),
+        max_fee_per_gas: ethers_tx.max_fee_per_gas.map(|v| v.to_string()),
+        max_priority_fee_per_gas: ethers_tx.max_priority_fee_per_gas.map(|v| v.to_string()),
         transaction_type: ethers_tx.transaction_type.map(|v| v.as_u64()),
-        max_fee_per_blob_gas: other_fields.get("maxFeePerBlobGas").and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()),
+        max_fee_per_blob_gas: max_fee_per_blob_gas.map(|v| v.to_string()),
         blob_versioned_hashes: other_fields.get("blobVersionedHashes").and_then(|v| v.as_array()).map(|hashes| {
             hashes.iter().filter_map(|h| h.as_str().map(|s| s.to_string())).collect()
         }).unwrap_or_default(),
+        receipt_status: None,
+        receipt_gas_used: None,
+        receipt_cumulative_gas_used: None,
+        receipt_effective_gas_price: None,
+        receipt_contract_address: None,
+        receipt_blob_gas_used: None,
+        receipt_blob_gas_price: None,
+        receipt_logs_count: None,
+        access_list: ethers_tx.access_list.map(|access_list| {
+            access_list
+                .0
+                .into_iter()
+                .map(|item| AccessListItem {
+                    address: format!("{:#x}", item.address),
+                    storage_keys: item.storage_keys.iter().map(|key| format!("{:#x}", key)).collect(),
+                })
+                .collect()
+        }),
+        authorization_list: authorization_list
+            .map(|list| list.into_iter().map(signed_authorization_to_authorization).collect()),
+        v: Some(v),
+        r: Some(format!("{:#x}", ethers_tx.r)),
+        s: Some(format!("{:#x}", ethers_tx.s)),
+        y_parity,
+        chain_id: ethers_tx
+            .chain_id
+            .map(|chain_id| chain_id.as_u64())
+            .or_else(|| legacy_chain_id(v).filter(|_| !is_typed)),
+        recovered_from_address: None,
+        from_address_mismatch: None,
+        method_signature: None,
+        method_name: None,
+        decoded_args: None,
+        effective_gas_price: None,
+        burnt_fee: None,
+        priority_fee_paid: None,
+        blob_fee: None,
+        total_fee: None,
+    }
+}
+
+fn signed_authorization_to_authorization(authorization: SignedAuthorization) -> Authorization {
+    Authorization {
+        chain_id: authorization.chain_id.to_string(),
+        address: format!("{:#x}", authorization.address),
+        nonce: authorization.nonce.as_u64(),
+        y_parity: authorization.y_parity.as_u64(),
+        r: format!("{:#x}", authorization.r),
+        s: format!("{:#x}", authorization.s),
+        authority: authorization
+            .recover_authority()
+            .map(|authority| format!("{:#x}", authority)),
+    }
+}
+
+pub fn transaction_to_authorizations(transaction: &Transaction) -> Vec<TransactionAuthorization> {
+    transaction
+        .authorization_list
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, authorization)| TransactionAuthorization {
+            transaction_hash: transaction.hash.clone(),
+            block_number: transaction.block_number,
+            transaction_index: transaction.transaction_index,
+            authorization_index: index as u64,
+            authorization: authorization.clone(),
+        })
+        .collect()
+}
+
+pub fn transaction_to_access_list_entries(transaction: &Transaction) -> Vec<TransactionAccessListEntry> {
+    let entry = |address: &str, storage_key: Option<&String>| TransactionAccessListEntry {
+        transaction_hash: transaction.hash.clone(),
+        block_number: transaction.block_number,
+        transaction_index: transaction.transaction_index,
+        address: address.to_string(),
+        storage_key: storage_key.cloned(),
+    };
+
+    transaction
+        .access_list
+        .iter()
+        .flatten()
+        .flat_map(|item| {
+            if item.storage_keys.is_empty() {
+                vec![entry(&item.address, None)]
+            } else {
+                item.storage_keys.iter().map(|key| entry(&item.address, Some(key))).collect()
+            }
+        })
+        .collect()
+}
+
+pub fn enrich_transaction_with_receipt(transaction: &mut Transaction, receipt: &Receipt) {
+    transaction.receipt_status = receipt.status;
+    transaction.receipt_gas_used = receipt.gas_used;
+    transaction.receipt_cumulative_gas_used = Some(receipt.cumulative_gas_used);
+    transaction.receipt_effective_gas_price = receipt.effective_gas_price.clone();
+    transaction.receipt_contract_address = receipt.contract_address.clone();
+    transaction.receipt_blob_gas_used = receipt.blob_gas_used;
+    transaction.receipt_blob_gas_price = receipt.blob_gas_price;
+    transaction.receipt_logs_count = Some(receipt.logs.len() as u64);
+}
+
+/// Records the sender recovered from the signature and whether it differs from the `from` the
+/// node reports. A signature that recovers no address is a mismatch.
+pub fn enrich_transaction_with_recovered_sender(
+    transaction: &mut Transaction,
+    recovered_sender: Result<Option<Address>, SignatureError>,
+) {
+    match recovered_sender {
+        Ok(Some(sender)) => {
+            let sender = format!("{:#x}", sender);
+            transaction.from_address_mismatch = Some(sender != transaction.from_address);
+            transaction.recovered_from_address = Some(sender);
+        }
+        Ok(None) => {}
+        Err(_) => transaction.from_address_mismatch = Some(true),
+    }
+}
+
+/// Splits what a transaction paid into the EIP-1559 burn, the priority fee that goes to the block
+/// producer and the EIP-4844 blob fee, which is burnt as well.
+///
+/// The effective gas price comes from the receipt, or else from the fee caps and the block's base
+/// fee. The fees need the gas used from the receipt and are left empty without one. Before London
+/// there is no base fee and the whole gas fee is priority fee. Amounts are computed in 256 bits,
+/// since gas times price can exceed 64 bits.
+pub fn calculate_transaction_fees(transaction: &mut Transaction, base_fee_per_gas: Option<u64>) {
+    let base_fee_per_gas = base_fee_per_gas.map(U256::from);
+    let receipt_effective_gas_price = parse_wei(&transaction.receipt_effective_gas_price);
+    let effective_gas_price = match (receipt_effective_gas_price, parse_wei(&transaction.max_fee_per_gas), base_fee_per_gas) {
+        (Some(effective_gas_price), _, _) => effective_gas_price,
+        (None, Some(max_fee_per_gas), Some(base_fee_per_gas)) => {
+            let max_priority_fee_per_gas = parse_wei(&transaction.max_priority_fee_per_gas).unwrap_or_default();
+            max_fee_per_gas.min(base_fee_per_gas.saturating_add(max_priority_fee_per_gas))
+        }
+        _ => match U256::from_dec_str(&transaction.gas_price) {
+            Ok(gas_price) => gas_price,
+            Err(_) => return,
+        },
+    };
+    transaction.effective_gas_price = Some(effective_gas_price.to_string());
+
+    let Some(gas_used) = transaction.receipt_gas_used.map(U256::from) else {
+        return;
+    };
+    let gas_fee = effective_gas_price.saturating_mul(gas_used);
+    let burnt_fee = base_fee_per_gas.map(|base_fee_per_gas| base_fee_per_gas.saturating_mul(gas_used));
+    let blob_fee = match (transaction.receipt_blob_gas_used, transaction.receipt_blob_gas_price) {
+        (Some(blob_gas_used), Some(blob_gas_price)) => Some(U256::from(blob_gas_used).saturating_mul(U256::from(blob_gas_price))),
+        _ => None,
+    };
+
+    transaction.burnt_fee = burnt_fee.map(|fee| fee.to_string());
+    transaction.priority_fee_paid = Some(gas_fee.saturating_sub(burnt_fee.unwrap_or_default()).to_string());
+    transaction.blob_fee = blob_fee.map(|fee| fee.to_string());
+    transaction.total_fee = Some(gas_fee.saturating_add(blob_fee.unwrap_or_default()).to_string());
+}
+
+fn parse_wei(value: &Option<String>) -> Option<U256> {
+    U256::from_dec_str(value.as_deref()?).ok()
+}
+
+pub fn ethers_receipt_to_receipt(ethers_receipt: EthersTransactionReceipt) -> Receipt {
+    Receipt {
+        transaction_hash: format!("{:#x}", ethers_receipt.transaction_hash),
+        transaction_index: ethers_receipt.transaction_index.as_u64(),
+        block_hash: ethers_receipt.block_hash.map(|h| format!("{:#x}", h)),
+        block_number: ethers_receipt.block_number.map(|bn| bn.as_u64()),
+        cumulative_gas_used: ethers_receipt.cumulative_gas_used.as_u64(),
+        gas_used: ethers_receipt.gas_used.map(|v| v.as_u64()),
+        contract_address: ethers_receipt.contract_address.map(|addr| format!("{:#x}", addr)),
+        root: ethers_receipt.root.map(|r| format!("{:#x}", r)),
+        status: ethers_receipt.status.map(|v| v.as_u64()),
+        effective_gas_price: ethers_receipt.effective_gas_price.map(|v| v.to_string()),
+        blob_gas_used: other_field_as_u64(&ethers_receipt.other, "blobGasUsed"),
+        blob_gas_price: other_field_as_u64(&ethers_receipt.other, "blobGasPrice"),
+        logs: ethers_receipt
+            .logs
+            .into_iter()
+            .map(ethers_log_to_log)
+            .collect(),
+    }
+}
+
+pub fn ethers_log_to_log(ethers_log: EthersLog) -> Log {
+    Log {
+        log_index: ethers_log.log_index.map(|i| i.as_u64()),
+        transaction_hash: ethers_log.transaction_hash.map(|h| format!("{:#x}", h)),
+        transaction_index: ethers_log.transaction_index.map(|i| i.as_u64()),
+        block_hash: ethers_log.block_hash.map(|h| format!("{:#x}", h)),
+        block_number: ethers_log.block_number.map(|bn| bn.as_u64()),
+        address: format!("{:#x}", ethers_log.address),
+        data: format!("{:#x}", ethers_log.data),
+        topics: ethers_log
+            .topics
+            .iter()
+            .map(|topic| format!("{:#x}", topic))
+            .collect(),
+    }
+}
+
+pub fn bytecode_to_contract(address: Address, bytecode: &Bytes, block_number: u64) -> Contract {
+    let function_sighashes = get_function_sighashes(bytecode);
+    let metadata = parse_compiler_metadata(bytecode);
+
+    Contract {
+        address: format!("{:#x}", address),
+        bytecode: format!("{:#x}", bytecode),
+        is_erc20: is_erc20_contract(&function_sighashes),
+        is_erc721: is_erc721_contract(&function_sighashes),
+        function_sighashes,
+        block_number,
+        proxy_type: None,
+        implementation_address: None,
+        proxy_admin_address: None,
+        beacon_address: None,
+        compiler: metadata.as_ref().and_then(|metadata| metadata.compiler.clone()),
+        compiler_version: metadata.as_ref().and_then(|metadata| metadata.compiler_version.clone()),
+        metadata_hash_type: metadata.as_ref().and_then(|metadata| metadata.metadata_hash_type.clone()),
+        metadata_hash: metadata.as_ref().and_then(|metadata| metadata.metadata_hash.clone()),
+        experimental: metadata.map(|metadata| metadata.experimental),
+    }
+}
+
+/// Maps a Parity/Erigon-style trace. `status` and `trace_id` depend on the other traces of the
+/// block and are filled in afterwards by [`crate::traces::calculate_trace_statuses`] and
+/// [`crate::traces::calculate_trace_ids`].
+pub fn ethers_trace_to_trace(ethers_trace: EthersTrace) -> Trace {
+    let mut trace = Trace {
+        block_number: ethers_trace.block_number,
+        transaction_hash: ethers_trace.transaction_hash.map(|h| format!("{:#x}", h)),
+        transaction_index: ethers_trace.transaction_position.map(|i| i as u64),
+        from_address: None,
+        to_address: None,
+        value: "0".to_string(),
+        input: None,
+        output: None,
+        trace_type: String::new(),
+        call_type: None,
+        reward_type: None,
+        gas: None,
+        gas_used: None,
+        subtraces: ethers_trace.subtraces as u64,
+        trace_address: ethers_trace.trace_address.iter().map(|i| *i as u64).collect(),
+        error: ethers_trace.error,
+        status: 1,
+        trace_id: String::new(),
+    };
+
+    match ethers_trace.action {
+        Action::Call(call) => {
+            trace.trace_type = "call".to_string();
+            trace.from_address = Some(format!("{:#x}", call.from));
+            trace.to_address = Some(format!("{:#x}", call.to));
+            trace.value = call.value.to_string();
+            trace.input = Some(format!("{:#x}", call.input));
+            trace.gas = Some(call.gas.as_u64());
+            trace.call_type = Some(call_type_to_string(&call.call_type).to_string());
+        }
+        Action::Create(create) => {
+            trace.trace_type = "create".to_string();
+            trace.from_address = Some(format!("{:#x}", create.from));
+            trace.value = create.value.to_string();
+            trace.input = Some(format!("{:#x}", create.init));
+            trace.gas = Some(create.gas.as_u64());
+        }
+        Action::Suicide(suicide) => {
+            trace.trace_type = "suicide".to_string();
+            trace.from_address = Some(format!("{:#x}", suicide.address));
+            trace.to_address = Some(format!("{:#x}", suicide.refund_address));
+            trace.value = suicide.balance.to_string();
+        }
+        Action::Reward(reward) => {
+            trace.trace_type = "reward".to_string();
+            trace.to_address = Some(format!("{:#x}", reward.author));
+            trace.value = reward.value.to_string();
+            trace.reward_type = Some(reward_type_to_string(&reward.reward_type).to_string());
+        }
+    }
+
+    match ethers_trace.result {
+        Some(Res::Call(result)) => {
+            trace.output = Some(format!("{:#x}", result.output));
+            trace.gas_used = Some(result.gas_used.as_u64());
+        }
+        Some(Res::Create(result)) => {
+            trace.to_address = Some(format!("{:#x}", result.address));
+            trace.output = Some(format!("{:#x}", result.code));
+            trace.gas_used = Some(result.gas_used.as_u64());
+        }
+        Some(Res::None) | None => {}
+    }
+
+    trace
+}
+
+fn call_type_to_string(call_type: &CallType) -> &'static str {
+    match call_type {
+        CallType::None => "",
+        CallType::Call => "call",
+        CallType::CallCode => "callcode",
+        CallType::DelegateCall => "delegatecall",
+        CallType::StaticCall => "staticcall",
+    }
+}
+
+fn reward_type_to_string(reward_type: &RewardType) -> &'static str {
+    match reward_type {
+        RewardType::Block => "block",
+        RewardType::Uncle => "uncle",
+        RewardType::EmptyStep => "emptyStep",
+        RewardType::External => "external",
+    }
+}
+
+/// A row of the logs CSV written by `export_receipts_and_logs`.
+#[derive(Debug, serde::Deserialize)]
+pub struct LogCsvRow {
+    log_index: Option<u64>,
+    transaction_hash: Option<String>,
+    transaction_index: Option<u64>,
+    block_hash: Option<String>,
+    block_number: Option<u64>,
+    address: String,
+    data: String,
+    topics: String,
+}
+
+pub fn log_csv_row_to_log(row: LogCsvRow) -> Log {
+    Log {
+        log_index: row.log_index,
+        transaction_hash: row.transaction_hash,
+        transaction_index: row.transaction_index,
+        block_hash: row.block_hash,
+        block_number: row.block_number,
+        address: row.address,
+        data: row.data,
+        topics: row
+            .topics
+            .split(',')
+            .filter(|topic| !topic.is_empty())
+            .map(|topic| topic.to_string())
+            .collect(),
+    }
+}
+
+/// Reads a hash that ethers does not model yet from the `other` map of a response.
+fn other_field_as_h256(other: &OtherFields, key: &str) -> Option<H256> {
+    other.get_deserialized(key)?.ok()
+}
+
+/// Reads a hex quantity that may not fit in 64 bits, such as a fee cap.
+fn other_field_as_u256(other: &OtherFields, key: &str) -> Option<U256> {
+    other.get_deserialized(key)?.ok()
+}
+
+/// Reads a quantity that ethers does not model yet from the `other` map of a response.
+/// Nodes return these as hex quantities, but decimal strings and plain numbers are accepted too.
+fn other_field_as_u64(other: &OtherFields, key: &str) -> Option<u64> {
+    match other.get(key)? {
+        serde_json::Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse::<u64>().ok(),
+        },
+        serde_json::Value::Number(n) => n.as_u64(),
+        _ => None,
     }
 }
 
+type BlockColumn = fn(&Block) -> String;
+
+/// The block CSV columns in order, each with the function that renders it. Both the header and
+/// [`block_to_csv_row`] are derived from this table, so a new header field only needs an entry here.
+const BLOCK_COLUMNS: &[(&str, BlockColumn)] = &[
+    ("number", |block| block.number.to_string()),
+    ("hash", |block| block.hash.clone()),
+    ("parent_hash", |block| block.parent_hash.clone()),
+    ("nonce", |block| block.nonce.clone()),
+    ("sha3_uncles", |block| block.sha3_uncles.clone()),
+    ("logs_bloom", |block| block.logs_bloom.clone()),
+    ("transactions_root", |block| block.transactions_root.clone()),
+    ("state_root", |block| block.state_root.clone()),
+    ("receipts_root", |block| block.receipts_root.clone()),
+    ("miner", |block| block.miner.clone()),
+    ("difficulty", |block| block.difficulty.clone()),
+    ("total_difficulty", |block| block.total_difficulty.clone().unwrap_or_else(|| "0".to_string())),
+    ("size", |block| block.size.to_string()),
+    ("extra_data", |block| block.extra_data.clone()),
+    ("gas_limit", |block| block.gas_limit.to_string()),
+    ("gas_used", |block| block.gas_used.to_string()),
+    ("timestamp", |block| block.timestamp.to_string()),
+    ("transaction_count", |block| block.transaction_count.to_string()),
+    ("base_fee_per_gas", |block| block.base_fee_per_gas.map(|num| num.to_string()).unwrap_or_default()),
+    ("withdrawals_root", |block| block.withdrawals_root.clone().unwrap_or_default()),
+    ("blob_gas_used", |block| block.blob_gas_used.map(|num| num.to_string()).unwrap_or_default()),
+    ("excess_blob_gas", |block| block.excess_blob_gas.map(|num| num.to_string()).unwrap_or_default()),
+    ("parent_beacon_block_root", |block| block.parent_beacon_block_root.clone().unwrap_or_default()),
+    ("requests_hash", |block| block.requests_hash.clone().unwrap_or_default()),
+    ("mix_hash", |block| block.mix_hash.clone().unwrap_or_default()),
+    ("withdrawals_count", |block| block.withdrawals_count.map(|num| num.to_string()).unwrap_or_default()),
+];
+
+pub fn block_csv_header() -> Vec<&'static str> {
+    BLOCK_COLUMNS.iter().map(|(name, _)| *name).collect()
+}
+
 pub fn block_to_csv_row(block: &Block) -> Vec<String> {
-    vec![
-        block.number.to_string(),
-        block.hash.clone(),
-        block.parent_hash.clone(),
-        block.nonce.clone(),
-        block.sha3_uncles.clone(),
-        block.logs_bloom.clone(),
-        block.transactions_root.clone(),
-        block.state_root.clone(),
-        block.receipts_root.clone(),
-        block.miner.clone(),
-        block.difficulty.clone(),
-        block.total_difficulty.clone().unwrap_or_else(|| "0".to_string()),
-        block.size.to_string(),
-        block.extra_data.clone(),
-        block.gas_limit.to_string(),
-        block.gas_used.to_string(),
-        block.timestamp.to_string(),
-        block.transaction_count.to_string(),
-        block.base_fee_per_gas.map(|num| num.to_string()).unwrap_or_default(),
-        block.withdrawals_root.clone().unwrap_or_else(|| "".to_string()),
-        block.blob_gas_used.map(|num| num.to_string()).unwrap_or_default(),
-        block.excess_blob_gas.map(|num| num.to_string()).unwrap_or_default(),
-    ]
+    BLOCK_COLUMNS.iter().map(|(_, column)| column(block)).collect()
+}
+
+/// Uncle rows are block rows followed by the including block's number and hash and the
+/// position of the uncle in that block.
+pub fn uncle_to_csv_row(uncle: &Uncle) -> Vec<String> {
+    let mut row = block_to_csv_row(&uncle.block);
+    row.extend([
+        uncle.including_block_number.to_string(),
+        uncle.including_block_hash.clone(),
+        uncle.uncle_index.to_string(),
+    ]);
+    row
 }
 
 pub fn transaction_to_csv_row(transaction: &Transaction) -> Vec<String> {
     vec![
         transaction.hash.clone(),
         transaction.nonce.to_string(),
-        transaction.block_hash.clone().unwrap_or_else(|| "".to_string()),
+        transaction.block_hash.clone().unwrap_or_default(),
         transaction.block_number.map(|num| num.to_string()).unwrap_or_default(),
         transaction.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
         transaction.from_address.clone(),
-        transaction.to_address.clone().unwrap_or_else(|| "".to_string()),
+        transaction.to_address.clone().unwrap_or_default(),
         transaction.value.clone(),
         transaction.gas.to_string(),
         transaction.gas_price.to_string(),
         transaction.input.clone(),
         transaction.block_timestamp.to_string(),
-        transaction.max_fee_per_gas.map(|num| num.to_string()).unwrap_or_default(),
-        transaction.max_priority_fee_per_gas.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.max_fee_per_gas.clone().unwrap_or_default(),
+        transaction.max_priority_fee_per_gas.clone().unwrap_or_default(),
         transaction.transaction_type.map(|num| num.to_string()).unwrap_or_default(),
-        transaction.max_fee_per_blob_gas.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.max_fee_per_blob_gas.clone().unwrap_or_default(),
         transaction.blob_versioned_hashes.join(","),
+        transaction.receipt_status.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.receipt_gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.receipt_cumulative_gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.receipt_effective_gas_price.clone().unwrap_or_default(),
+        transaction.receipt_contract_address.clone().unwrap_or_default(),
+        transaction.receipt_blob_gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.receipt_blob_gas_price.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.receipt_logs_count.map(|num| num.to_string()).unwrap_or_default(),
+        transaction
+            .access_list
+            .as_ref()
+            .map(|access_list| serde_json::to_string(access_list).unwrap_or_default())
+            .unwrap_or_default(),
+        transaction
+            .authorization_list
+            .as_ref()
+            .map(|authorization_list| serde_json::to_string(authorization_list).unwrap_or_default())
+            .unwrap_or_default(),
+        transaction.v.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.r.clone().unwrap_or_default(),
+        transaction.s.clone().unwrap_or_default(),
+        transaction.y_parity.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.chain_id.map(|num| num.to_string()).unwrap_or_default(),
+        transaction.recovered_from_address.clone().unwrap_or_default(),
+        transaction.from_address_mismatch.map(|mismatch| mismatch.to_string()).unwrap_or_default(),
+        transaction.method_signature.clone().unwrap_or_default(),
+        transaction.method_name.clone().unwrap_or_default(),
+        transaction.decoded_args.clone().unwrap_or_default(),
+        transaction.effective_gas_price.clone().unwrap_or_default(),
+        transaction.burnt_fee.clone().unwrap_or_default(),
+        transaction.priority_fee_paid.clone().unwrap_or_default(),
+        transaction.blob_fee.clone().unwrap_or_default(),
+        transaction.total_fee.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn transaction_authorization_to_csv_row(transaction_authorization: &TransactionAuthorization) -> Vec<String> {
+    let authorization = &transaction_authorization.authorization;
+    vec![
+        transaction_authorization.transaction_hash.clone(),
+        transaction_authorization.block_number.map(|num| num.to_string()).unwrap_or_default(),
+        transaction_authorization.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
+        transaction_authorization.authorization_index.to_string(),
+        authorization.chain_id.clone(),
+        authorization.address.clone(),
+        authorization.nonce.to_string(),
+        authorization.y_parity.to_string(),
+        authorization.r.clone(),
+        authorization.s.clone(),
+        authorization.authority.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn transaction_access_list_entry_to_csv_row(entry: &TransactionAccessListEntry) -> Vec<String> {
+    vec![
+        entry.transaction_hash.clone(),
+        entry.block_number.map(|num| num.to_string()).unwrap_or_default(),
+        entry.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
+        entry.address.clone(),
+        entry.storage_key.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn receipt_to_csv_row(receipt: &Receipt) -> Vec<String> {
+    vec![
+        receipt.transaction_hash.clone(),
+        receipt.transaction_index.to_string(),
+        receipt.block_hash.clone().unwrap_or_default(),
+        receipt.block_number.map(|num| num.to_string()).unwrap_or_default(),
+        receipt.cumulative_gas_used.to_string(),
+        receipt.gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        receipt.contract_address.clone().unwrap_or_default(),
+        receipt.root.clone().unwrap_or_default(),
+        receipt.status.map(|num| num.to_string()).unwrap_or_default(),
+        receipt.effective_gas_price.clone().unwrap_or_default(),
+        receipt.blob_gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        receipt.blob_gas_price.map(|num| num.to_string()).unwrap_or_default(),
+    ]
+}
+
+pub fn log_to_csv_row(log: &Log) -> Vec<String> {
+    vec![
+        log.log_index.map(|num| num.to_string()).unwrap_or_default(),
+        log.transaction_hash.clone().unwrap_or_default(),
+        log.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
+        log.block_hash.clone().unwrap_or_default(),
+        log.block_number.map(|num| num.to_string()).unwrap_or_default(),
+        log.address.clone(),
+        log.data.clone(),
+        log.topics.join(","),
+    ]
+}
+
+pub fn token_transfer_to_csv_row(token_transfer: &TokenTransfer) -> Vec<String> {
+    vec![
+        token_transfer.token_address.clone(),
+        token_transfer.from_address.clone(),
+        token_transfer.to_address.clone(),
+        token_transfer.value.clone(),
+        token_transfer.transaction_hash.clone().unwrap_or_default(),
+        token_transfer.log_index.map(|num| num.to_string()).unwrap_or_default(),
+        token_transfer.block_number.map(|num| num.to_string()).unwrap_or_default(),
+        token_transfer.operator.clone().unwrap_or_default(),
+        token_transfer.token_id.clone().unwrap_or_default(),
+        token_transfer.batch_index.map(|num| num.to_string()).unwrap_or_default(),
+        token_transfer.token_standard.clone(),
+    ]
+}
+
+pub fn contract_to_csv_row(contract: &Contract) -> Vec<String> {
+    vec![
+        contract.address.clone(),
+        contract.bytecode.clone(),
+        contract.function_sighashes.join(","),
+        contract.is_erc20.to_string(),
+        contract.is_erc721.to_string(),
+        contract.block_number.to_string(),
+        contract.proxy_type.clone().unwrap_or_default(),
+        contract.implementation_address.clone().unwrap_or_default(),
+        contract.proxy_admin_address.clone().unwrap_or_default(),
+        contract.beacon_address.clone().unwrap_or_default(),
+        contract.compiler.clone().unwrap_or_default(),
+        contract.compiler_version.clone().unwrap_or_default(),
+        contract.metadata_hash_type.clone().unwrap_or_default(),
+        contract.metadata_hash.clone().unwrap_or_default(),
+        contract.experimental.map(|experimental| experimental.to_string()).unwrap_or_default(),
+    ]
+}
+
+pub fn block_reward_to_csv_row(block_reward: &BlockReward) -> Vec<String> {
+    vec![
+        block_reward.block_number.to_string(),
+        block_reward.block_hash.clone(),
+        block_reward.miner.clone(),
+        block_reward.static_reward.clone(),
+        block_reward.uncle_inclusion_reward.clone(),
+        serde_json::to_string(&block_reward.uncle_rewards).unwrap_or_default(),
+        block_reward.total_priority_fees.clone().unwrap_or_default(),
+        block_reward.burnt_fees.clone().unwrap_or_default(),
+        block_reward.total_reward.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn eth_supply_to_csv_row(eth_supply: &EthSupply) -> Vec<String> {
+    vec![
+        eth_supply.block_number.to_string(),
+        eth_supply.block_hash.clone(),
+        eth_supply.issuance.clone(),
+        eth_supply.burnt_fees.clone(),
+        eth_supply.withdrawals.clone(),
+        eth_supply.blob_fees_burnt.clone(),
+        eth_supply.supply_delta.clone(),
+        eth_supply.supply.clone(),
+    ]
+}
+
+pub fn proxy_upgrade_to_csv_row(upgrade: &ProxyUpgrade) -> Vec<String> {
+    vec![
+        upgrade.block_number.to_string(),
+        upgrade.transaction_hash.clone(),
+        upgrade.log_index.to_string(),
+        upgrade.address.clone(),
+        upgrade.implementation_address.clone().unwrap_or_default(),
+        upgrade.beacon_address.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn token_to_csv_row(token: &Token) -> Vec<String> {
+    vec![
+        token.address.clone(),
+        token.symbol.clone().unwrap_or_default(),
+        token.name.clone().unwrap_or_default(),
+        token.decimals.map(|num| num.to_string()).unwrap_or_default(),
+        token.total_supply.clone().unwrap_or_default(),
+        token.block_number.to_string(),
+    ]
+}
+
+pub fn trace_to_csv_row(trace: &Trace) -> Vec<String> {
+    vec![
+        trace.block_number.to_string(),
+        trace.transaction_hash.clone().unwrap_or_default(),
+        trace.transaction_index.map(|num| num.to_string()).unwrap_or_default(),
+        trace.from_address.clone().unwrap_or_default(),
+        trace.to_address.clone().unwrap_or_default(),
+        trace.value.clone(),
+        trace.input.clone().unwrap_or_default(),
+        trace.output.clone().unwrap_or_default(),
+        trace.trace_type.clone(),
+        trace.call_type.clone().unwrap_or_default(),
+        trace.reward_type.clone().unwrap_or_default(),
+        trace.gas.map(|num| num.to_string()).unwrap_or_default(),
+        trace.gas_used.map(|num| num.to_string()).unwrap_or_default(),
+        trace.subtraces.to_string(),
+        trace
+            .trace_address
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        trace.error.clone().unwrap_or_default(),
+        trace.status.to_string(),
+        trace.trace_id.clone(),
+    ]
+}
+
+pub fn withdrawal_to_csv_row(withdrawal: &Withdrawal) -> Vec<String> {
+    vec![
+        withdrawal.index.to_string(),
+        withdrawal.validator_index.to_string(),
+        withdrawal.address.clone(),
+        withdrawal.amount.to_string(),
+        withdrawal.block_number.to_string(),
+        withdrawal.block_hash.clone(),
+    ]
+}
+
+pub fn balance_change_to_csv_row(balance_change: &BalanceChange) -> Vec<String> {
+    vec![
+        balance_change.block_number.to_string(),
+        balance_change.transaction_hash.clone(),
+        balance_change.transaction_index.to_string(),
+        balance_change.address.clone(),
+        balance_change.balance_before.clone().unwrap_or_default(),
+        balance_change.balance_after.clone().unwrap_or_default(),
+        balance_change.nonce_before.map(|num| num.to_string()).unwrap_or_default(),
+        balance_change.nonce_after.map(|num| num.to_string()).unwrap_or_default(),
+        balance_change.code_hash_before.clone().unwrap_or_default(),
+        balance_change.code_hash_after.clone().unwrap_or_default(),
     ]
-}
\ No newline at end of file
+}
+
+pub fn storage_change_to_csv_row(storage_change: &StorageChange) -> Vec<String> {
+    vec![
+        storage_change.block_number.to_string(),
+        storage_change.transaction_hash.clone(),
+        storage_change.transaction_index.to_string(),
+        storage_change.address.clone(),
+        storage_change.slot.clone(),
+        storage_change.value_before.clone(),
+        storage_change.value_after.clone(),
+    ]
+}
+
+pub fn account_state_to_csv_row(account_state: &AccountState) -> Vec<String> {
+    vec![
+        account_state.block_number.to_string(),
+        account_state.address.clone(),
+        account_state.balance.clone(),
+        account_state.nonce.to_string(),
+        account_state.code_hash.clone(),
+    ]
+}
+
+pub fn token_balance_to_csv_row(token_balance: &TokenBalance) -> Vec<String> {
+    vec![
+        token_balance.block_number.to_string(),
+        token_balance.token_address.clone(),
+        token_balance.holder_address.clone(),
+        token_balance.balance.clone().unwrap_or_default(),
+    ]
+}
+
+pub fn execution_request_to_csv_row(request: &ExecutionRequest) -> Vec<String> {
+    vec![
+        request.block_number.to_string(),
+        request.block_hash.clone(),
+        request.request_type.to_string(),
+        request.request_index.to_string(),
+        request.source_address.clone().unwrap_or_default(),
+        request.pubkey.clone().unwrap_or_default(),
+        request.target_pubkey.clone().unwrap_or_default(),
+        request.withdrawal_credentials.clone().unwrap_or_default(),
+        request.amount.map(|num| num.to_string()).unwrap_or_default(),
+        request.signature.clone().unwrap_or_default(),
+        request.deposit_index.map(|num| num.to_string()).unwrap_or_default(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn transaction(fields: serde_json::Value) -> Transaction {
+        let mut transaction = json!({
+            "hash": "0x", "nonce": 0, "block_hash": null, "block_number": 19_426_587, "transaction_index": 0,
+            "from_address": "0x", "to_address": null, "value": "0", "gas": 21000, "gas_price": "0",
+            "input": "0x", "block_timestamp": 0,
+        });
+        for (key, value) in fields.as_object().unwrap() {
+            transaction[key] = value.clone();
+        }
+        serde_json::from_value(transaction).unwrap()
+    }
+
+    #[test]
+    fn pays_the_whole_gas_fee_as_priority_fee_before_london() {
+        let mut transaction = transaction(json!({"gas_price": (20 * GWEI).to_string(), "receipt_gas_used": 21000}));
+
+        calculate_transaction_fees(&mut transaction, None);
+
+        assert_eq!(transaction.effective_gas_price.as_deref(), Some("20000000000"));
+        assert_eq!(transaction.burnt_fee, None);
+        assert_eq!(transaction.priority_fee_paid.as_deref(), Some("420000000000000"));
+        assert_eq!(transaction.blob_fee, None);
+        assert_eq!(transaction.total_fee.as_deref(), Some("420000000000000"));
+    }
+
+    #[test]
+    fn caps_the_effective_gas_price_without_a_receipt() {
+        let fee_caps = |max_fee_per_gas: u64| {
+            json!({
+                "transaction_type": 2,
+                "max_fee_per_gas": max_fee_per_gas.to_string(),
+                "max_priority_fee_per_gas": (2 * GWEI).to_string(),
+            })
+        };
+        let mut below_cap = transaction(fee_caps(100 * GWEI));
+        let mut at_cap = transaction(fee_caps(31 * GWEI));
+
+        calculate_transaction_fees(&mut below_cap, Some(30 * GWEI));
+        calculate_transaction_fees(&mut at_cap, Some(30 * GWEI));
+
+        assert_eq!(below_cap.effective_gas_price.as_deref(), Some("32000000000"));
+        assert_eq!(at_cap.effective_gas_price.as_deref(), Some("31000000000"));
+        assert_eq!(below_cap.burnt_fee, None);
+        assert_eq!(below_cap.total_fee, None);
+    }
+
+    #[test]
+    fn burns_the_base_fee_and_the_blob_fee() {
+        let mut transaction = transaction(json!({
+            "transaction_type": 3,
+            "max_fee_per_gas": (100 * GWEI).to_string(),
+            "max_priority_fee_per_gas": (2 * GWEI).to_string(),
+            "receipt_gas_used": 21000,
+            "receipt_effective_gas_price": (32 * GWEI).to_string(),
+            "receipt_blob_gas_used": 131072,
+            "receipt_blob_gas_price": 5 * GWEI,
+        }));
+
+        calculate_transaction_fees(&mut transaction, Some(30 * GWEI));
+
+        assert_eq!(transaction.burnt_fee.as_deref(), Some("630000000000000"));
+        assert_eq!(transaction.priority_fee_paid.as_deref(), Some("42000000000000"));
+        assert_eq!(transaction.blob_fee.as_deref(), Some("655360000000000"));
+        assert_eq!(transaction.total_fee.as_deref(), Some("1327360000000000"));
+    }
+
+    #[test]
+    fn computes_fees_beyond_64_bits() {
+        // One ether per gas on a full 30M gas block
+        let mut transaction = transaction(json!({
+            "gas": 30_000_000,
+            "gas_price": "1000000000000000000",
+            "receipt_gas_used": 30_000_000,
+        }));
+
+        calculate_transaction_fees(&mut transaction, None);
+
+        assert_eq!(transaction.priority_fee_paid.as_deref(), Some("30000000000000000000000000"));
+        assert_eq!(transaction.total_fee.as_deref(), Some("30000000000000000000000000"));
+    }
+
+    #[test]
+    fn reads_the_blob_fee_cap_as_a_hex_quantity() {
+        let ethers_transaction: EthersTransaction = serde_json::from_value(json!({
+            "hash": format!("0x{:064x}", 1), "nonce": "0x0", "blockHash": null, "blockNumber": null,
+            "transactionIndex": null, "from": format!("0x{:040x}", 1), "to": format!("0x{:040x}", 2),
+            "value": "0x0", "gasPrice": "0x0", "gas": "0x5208", "input": "0x", "v": "0x0", "r": "0x1",
+            "s": "0x1", "type": "0x3", "maxFeePerGas": "0x0", "maxPriorityFeePerGas": "0x0",
+            "maxFeePerBlobGas": "0x1bf08eb000", "blobVersionedHashes": [],
+        }))
+        .unwrap();
+
+        let transaction = ethers_transaction_to_transaction(ethers_transaction, 0);
+
+        assert_eq!(transaction.max_fee_per_blob_gas.as_deref(), Some("120000000000"));
+    }
+}
@@ -1,4 +1,4 @@
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::{Http, Provider};
 use url::Url;
 
 #[derive(Debug, thiserror::Error)]
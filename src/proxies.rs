@@ -0,0 +1,144 @@
+use crate::domain::ProxyUpgrade;
+use crate::tokens::call_view;
+use anyhow::Result;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Log as EthersLog, H256};
+
+/// `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+/// `keccak256("eip1967.proxy.admin") - 1`
+pub const EIP1967_ADMIN_SLOT: &str = "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+/// `keccak256("eip1967.proxy.beacon") - 1`
+pub const EIP1967_BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+/// `keccak256("PROXIABLE")`, the EIP-1822 (UUPS) implementation slot.
+pub const EIP1822_PROXIABLE_SLOT: &str = "0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7";
+
+/// `Upgraded(address indexed implementation)`
+pub const UPGRADED_EVENT_TOPIC: &str = "0xbc7cd75a20ee27fd9adebab32041f755214dbc6bffa90cc0225b39da2e5c2d3b";
+/// `BeaconUpgraded(address indexed beacon)`
+pub const BEACON_UPGRADED_EVENT_TOPIC: &str = "0x1cf3b03a6cf19fa2baba4df148e9dcabedea7f8a5c07840e207e5c089be95d3e";
+
+const IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b]; // implementation()
+
+/// The EIP-1167 runtime bytecode around the 20-byte implementation address.
+const MINIMAL_PROXY_PREFIX: [u8; 10] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+const MINIMAL_PROXY_SUFFIX: [u8; 15] = [
+    0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3,
+];
+
+pub const EIP1167_PROXY_TYPE: &str = "eip1167";
+pub const EIP1967_PROXY_TYPE: &str = "eip1967";
+pub const EIP1967_BEACON_PROXY_TYPE: &str = "eip1967_beacon";
+pub const EIP1822_PROXY_TYPE: &str = "eip1822";
+
+/// How a proxy delegates its calls and where to, as found at one block.
+pub struct Proxy {
+    pub proxy_type: &'static str,
+    pub implementation_address: Option<Address>,
+    pub admin_address: Option<Address>,
+    pub beacon_address: Option<Address>,
+}
+
+/// The implementation of an EIP-1167 minimal proxy, which is hard-coded in its bytecode.
+pub fn minimal_proxy_implementation(bytecode: &[u8]) -> Option<Address> {
+    let rest = bytecode.strip_prefix(&MINIMAL_PROXY_PREFIX[..])?;
+    let implementation = rest.strip_suffix(&MINIMAL_PROXY_SUFFIX[..])?;
+    (implementation.len() == 20).then(|| Address::from_slice(implementation))
+}
+
+/// Detects the proxy standard a contract follows at `block_number`.
+///
+/// Minimal proxies are recognised from their bytecode. Otherwise the EIP-1967 implementation and
+/// beacon slots and the EIP-1822 slot are read in that order, and the first one that holds an
+/// address decides. A beacon proxy's implementation is the one its beacon returns from
+/// `implementation()`.
+pub async fn detect_proxy<M>(provider: &M, address: Address, bytecode: &[u8], block_number: u64) -> Result<Option<Proxy>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    if let Some(implementation) = minimal_proxy_implementation(bytecode) {
+        return Ok(Some(Proxy {
+            proxy_type: EIP1167_PROXY_TYPE,
+            implementation_address: Some(implementation),
+            admin_address: None,
+            beacon_address: None,
+        }));
+    }
+
+    let implementation = read_address_slot(provider, address, EIP1967_IMPLEMENTATION_SLOT, block_number).await?;
+    let beacon = match implementation {
+        Some(_) => None,
+        None => read_address_slot(provider, address, EIP1967_BEACON_SLOT, block_number).await?,
+    };
+
+    if implementation.is_some() || beacon.is_some() {
+        let admin = read_address_slot(provider, address, EIP1967_ADMIN_SLOT, block_number).await?;
+        let (proxy_type, implementation) = match beacon {
+            Some(beacon) => (EIP1967_BEACON_PROXY_TYPE, beacon_implementation(provider, beacon, block_number).await?),
+            None => (EIP1967_PROXY_TYPE, implementation),
+        };
+        return Ok(Some(Proxy {
+            proxy_type,
+            implementation_address: implementation,
+            admin_address: admin,
+            beacon_address: beacon,
+        }));
+    }
+
+    let implementation = read_address_slot(provider, address, EIP1822_PROXIABLE_SLOT, block_number).await?;
+    Ok(implementation.map(|implementation| Proxy {
+        proxy_type: EIP1822_PROXY_TYPE,
+        implementation_address: Some(implementation),
+        admin_address: None,
+        beacon_address: None,
+    }))
+}
+
+/// Decodes an `Upgraded` or `BeaconUpgraded` log. The emitter is a proxy, or for `Upgraded` also
+/// an upgradeable beacon.
+pub fn log_to_proxy_upgrade(log: &EthersLog) -> Option<ProxyUpgrade> {
+    let topic = format!("{:#x}", log.topics.first()?);
+    let new_address = format!("{:#x}", Address::from(*log.topics.get(1)?));
+    let (implementation_address, beacon_address) = if topic == UPGRADED_EVENT_TOPIC {
+        (Some(new_address), None)
+    } else if topic == BEACON_UPGRADED_EVENT_TOPIC {
+        (None, Some(new_address))
+    } else {
+        return None;
+    };
+
+    Some(ProxyUpgrade {
+        block_number: log.block_number?.as_u64(),
+        transaction_hash: format!("{:#x}", log.transaction_hash?),
+        log_index: log.log_index?.as_u64(),
+        address: format!("{:#x}", log.address),
+        implementation_address,
+        beacon_address,
+    })
+}
+
+/// Reads a storage slot that holds an address in its low 20 bytes. Empty slots read as `None`.
+async fn read_address_slot<M>(provider: &M, address: Address, slot: &str, block_number: u64) -> Result<Option<Address>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let value = provider
+        .get_storage_at(address, slot.parse::<H256>()?, Some(block_number.into()))
+        .await?;
+    let slot_address = Address::from(value);
+    Ok((!slot_address.is_zero()).then_some(slot_address))
+}
+
+async fn beacon_implementation<M>(provider: &M, beacon: Address, block_number: u64) -> Result<Option<Address>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let output = call_view(provider, beacon, IMPLEMENTATION_SELECTOR.to_vec(), block_number).await?;
+    Ok(output
+        .filter(|output| output.len() == 32)
+        .map(|output| Address::from(H256::from_slice(&output)))
+        .filter(|implementation| !implementation.is_zero()))
+}
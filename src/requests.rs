@@ -0,0 +1,363 @@
+use crate::domain::ExecutionRequest;
+use ethers::abi::{decode, ParamType};
+use ethers::types::H256;
+use sha2::{Digest, Sha256};
+
+pub const DEPOSIT_REQUEST_TYPE: u64 = 0x00;
+pub const WITHDRAWAL_REQUEST_TYPE: u64 = 0x01;
+pub const CONSOLIDATION_REQUEST_TYPE: u64 = 0x02;
+
+pub const MAINNET_DEPOSIT_CONTRACT_ADDRESS: &str = "0x00000000219ab540356cBB839Cbe05303d7705Fa";
+/// `DepositEvent(bytes,bytes,bytes,bytes,bytes)` emitted by the deposit contract (EIP-6110).
+pub const DEPOSIT_EVENT_TOPIC: &str = "0x649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5";
+/// EIP-7002 withdrawal request predeploy.
+pub const WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS: &str = "0x00000961Ef480Eb55e80D19ad83579A64c007002";
+/// EIP-7251 consolidation request predeploy.
+pub const CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS: &str = "0x0000BBdDc7CE488642fb579F8B00f3a590007251";
+/// The caller of the end-of-block system calls that dequeue requests from the predeploys.
+pub const SYSTEM_ADDRESS: &str = "0xfffffffffffffffffffffffffffffffffffffffe";
+
+const MAX_WITHDRAWAL_REQUESTS_PER_BLOCK: usize = 16;
+const MAX_CONSOLIDATION_REQUESTS_PER_BLOCK: usize = 2;
+
+const PUBKEY_SIZE: usize = 48;
+/// `source_address ++ validator_pubkey ++ amount`
+const WITHDRAWAL_REQUEST_SIZE: usize = 20 + PUBKEY_SIZE + 8;
+/// `source_address ++ source_pubkey ++ target_pubkey`
+const CONSOLIDATION_REQUEST_SIZE: usize = 20 + PUBKEY_SIZE + PUBKEY_SIZE;
+
+/// What is known about a block's requests before its end-of-block system calls run: the data of
+/// the deposit logs, and for each predeploy the queue head left by the parent block (the output of
+/// the system call at the parent state) and the data of the requests added in this block's logs.
+#[derive(Default)]
+pub struct BlockRequestSources {
+    pub deposit_logs: Vec<Vec<u8>>,
+    pub pending_withdrawal_requests: Vec<u8>,
+    pub withdrawal_request_logs: Vec<Vec<u8>>,
+    pub pending_consolidation_requests: Vec<u8>,
+    pub consolidation_request_logs: Vec<Vec<u8>>,
+}
+
+/// Rebuilds the requests of a block in the order they are committed to by `requests_hash`.
+///
+/// The predeploys are FIFO queues and the system call dequeues at most a fixed number of requests
+/// per block, so the block's requests are the first ones of the queue left by the parent block
+/// followed by the requests added in the block. The system call returns amounts little-endian as
+/// the consensus layer expects, while the logs carry the big-endian amount from the calldata.
+pub fn block_requests(block_number: u64, block_hash: &str, sources: &BlockRequestSources) -> Vec<ExecutionRequest> {
+    let mut requests = vec![];
+
+    requests.extend(
+        sources
+            .deposit_logs
+            .iter()
+            .filter_map(|data| decode_deposit_event(data)),
+    );
+
+    let withdrawal_requests = sources
+        .pending_withdrawal_requests
+        .chunks_exact(WITHDRAWAL_REQUEST_SIZE)
+        .map(|request| decode_withdrawal_request(request, u64::from_le_bytes))
+        .chain(
+            sources
+                .withdrawal_request_logs
+                .iter()
+                .filter(|data| data.len() == WITHDRAWAL_REQUEST_SIZE)
+                .map(|data| decode_withdrawal_request(data, u64::from_be_bytes)),
+        )
+        .take(MAX_WITHDRAWAL_REQUESTS_PER_BLOCK);
+    requests.extend(withdrawal_requests);
+
+    let consolidation_requests = sources
+        .pending_consolidation_requests
+        .chunks_exact(CONSOLIDATION_REQUEST_SIZE)
+        .chain(
+            sources
+                .consolidation_request_logs
+                .iter()
+                .filter(|data| data.len() == CONSOLIDATION_REQUEST_SIZE)
+                .map(|data| data.as_slice()),
+        )
+        .map(decode_consolidation_request)
+        .take(MAX_CONSOLIDATION_REQUESTS_PER_BLOCK);
+    requests.extend(consolidation_requests);
+
+    let mut next_index = [0u64; 3];
+    for request in requests.iter_mut() {
+        request.block_number = block_number;
+        request.block_hash = block_hash.to_string();
+        request.request_index = next_index[request.request_type as usize];
+        next_index[request.request_type as usize] += 1;
+    }
+
+    requests
+}
+
+/// Decodes `DepositEvent(pubkey, withdrawal_credentials, amount, signature, index)`. The deposit
+/// contract encodes `amount` (in gwei) and `index` as 8 little-endian bytes.
+fn decode_deposit_event(data: &[u8]) -> Option<ExecutionRequest> {
+    let tokens = decode(&vec![ParamType::Bytes; 5], data).ok()?;
+    let mut fields = tokens.into_iter().map(|token| token.into_bytes());
+    let pubkey = fields.next()??;
+    let withdrawal_credentials = fields.next()??;
+    let amount = fields.next()??;
+    let signature = fields.next()??;
+    let index = fields.next()??;
+
+    Some(ExecutionRequest {
+        request_type: DEPOSIT_REQUEST_TYPE,
+        pubkey: Some(to_hex(&pubkey)),
+        withdrawal_credentials: Some(to_hex(&withdrawal_credentials)),
+        amount: Some(u64::from_le_bytes(amount.try_into().ok()?)),
+        signature: Some(to_hex(&signature)),
+        deposit_index: Some(u64::from_le_bytes(index.try_into().ok()?)),
+        ..Default::default()
+    })
+}
+
+fn decode_withdrawal_request(data: &[u8], decode_amount: fn([u8; 8]) -> u64) -> ExecutionRequest {
+    let (source_address, rest) = data.split_at(20);
+    let (pubkey, amount) = rest.split_at(PUBKEY_SIZE);
+
+    ExecutionRequest {
+        request_type: WITHDRAWAL_REQUEST_TYPE,
+        source_address: Some(to_hex(source_address)),
+        pubkey: Some(to_hex(pubkey)),
+        amount: Some(decode_amount(amount.try_into().unwrap_or_default())),
+        ..Default::default()
+    }
+}
+
+fn decode_consolidation_request(data: &[u8]) -> ExecutionRequest {
+    let (source_address, rest) = data.split_at(20);
+    let (source_pubkey, target_pubkey) = rest.split_at(PUBKEY_SIZE);
+
+    ExecutionRequest {
+        request_type: CONSOLIDATION_REQUEST_TYPE,
+        source_address: Some(to_hex(source_address)),
+        pubkey: Some(to_hex(source_pubkey)),
+        target_pubkey: Some(to_hex(target_pubkey)),
+        ..Default::default()
+    }
+}
+
+/// Computes the EIP-7685 `requests_hash` of a block's requests:
+/// `sha256(sha256(request_type ++ request_data) ++ ...)` over the request types in order, where
+/// `request_data` concatenates the encoded requests of the type and types without requests are
+/// left out. Returns `None` when a request lacks a field of its encoding.
+pub fn requests_hash(requests: &[ExecutionRequest]) -> Option<H256> {
+    let mut hasher = Sha256::new();
+    for request_type in [DEPOSIT_REQUEST_TYPE, WITHDRAWAL_REQUEST_TYPE, CONSOLIDATION_REQUEST_TYPE] {
+        let mut request_data = vec![];
+        for request in requests.iter().filter(|request| request.request_type == request_type) {
+            request_data.extend(encode_request(request)?);
+        }
+        if !request_data.is_empty() {
+            hasher.update(Sha256::digest([&[request_type as u8][..], &request_data].concat()));
+        }
+    }
+    Some(H256::from_slice(&hasher.finalize()))
+}
+
+/// Encodes a request the way the consensus layer receives it, with amounts and deposit indexes as
+/// 8 little-endian bytes.
+fn encode_request(request: &ExecutionRequest) -> Option<Vec<u8>> {
+    let fields = match request.request_type {
+        DEPOSIT_REQUEST_TYPE => vec![
+            from_hex(request.pubkey.as_deref()?)?,
+            from_hex(request.withdrawal_credentials.as_deref()?)?,
+            request.amount?.to_le_bytes().to_vec(),
+            from_hex(request.signature.as_deref()?)?,
+            request.deposit_index?.to_le_bytes().to_vec(),
+        ],
+        WITHDRAWAL_REQUEST_TYPE => vec![
+            from_hex(request.source_address.as_deref()?)?,
+            from_hex(request.pubkey.as_deref()?)?,
+            request.amount?.to_le_bytes().to_vec(),
+        ],
+        CONSOLIDATION_REQUEST_TYPE => vec![
+            from_hex(request.source_address.as_deref()?)?,
+            from_hex(request.pubkey.as_deref()?)?,
+            from_hex(request.target_pubkey.as_deref()?)?,
+        ],
+        _ => return None,
+    };
+    Some(fields.concat())
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    const BLOCK_HASH: &str = "0x9a1f0c6a3a6e6b5b3d2f5bb3bfa6b8a7f41e5b1b6c0ef4d1c8e5b9b4c1f0a2d3";
+
+    fn repeat(byte: u8, len: usize) -> Vec<u8> {
+        vec![byte; len]
+    }
+
+    /// The data of a `DepositEvent` log of 32 ETH.
+    fn deposit_log(index: u64) -> Vec<u8> {
+        let withdrawal_credentials = [vec![0x01], repeat(0, 11), repeat(0xd1, 20)].concat();
+        encode(&[
+            Token::Bytes(repeat(0xa1, PUBKEY_SIZE)),
+            Token::Bytes(withdrawal_credentials),
+            Token::Bytes(32_000_000_000u64.to_le_bytes().to_vec()),
+            Token::Bytes(repeat(0xb2, 96)),
+            Token::Bytes(index.to_le_bytes().to_vec()),
+        ])
+    }
+
+    fn withdrawal_request(source: u8, pubkey: u8, amount: [u8; 8]) -> Vec<u8> {
+        [repeat(source, 20), repeat(pubkey, PUBKEY_SIZE), amount.to_vec()].concat()
+    }
+
+    fn consolidation_request(source: u8, source_pubkey: u8, target_pubkey: u8) -> Vec<u8> {
+        [
+            repeat(source, 20),
+            repeat(source_pubkey, PUBKEY_SIZE),
+            repeat(target_pubkey, PUBKEY_SIZE),
+        ]
+        .concat()
+    }
+
+    /// A block with a deposit, two withdrawal requests left in the queue by the parent block and
+    /// one added by the block, and one queued and two added consolidation requests.
+    fn sources() -> BlockRequestSources {
+        BlockRequestSources {
+            deposit_logs: vec![deposit_log(1_900_000)],
+            pending_withdrawal_requests: [
+                withdrawal_request(0x11, 0xc1, 0u64.to_le_bytes()),
+                withdrawal_request(0x12, 0xc2, 1_000_000_000u64.to_le_bytes()),
+            ]
+            .concat(),
+            withdrawal_request_logs: vec![withdrawal_request(0x13, 0xc3, 2_000_000_000u64.to_be_bytes())],
+            pending_consolidation_requests: consolidation_request(0x21, 0xe1, 0xe2),
+            consolidation_request_logs: vec![
+                consolidation_request(0x22, 0xe3, 0xe4),
+                consolidation_request(0x23, 0xe5, 0xe6),
+            ],
+        }
+    }
+
+    fn hex_of(byte: u8, len: usize) -> Option<String> {
+        Some(to_hex(&repeat(byte, len)))
+    }
+
+    #[test]
+    fn decodes_deposit_logs() {
+        let requests = block_requests(22_431_084, BLOCK_HASH, &sources());
+        let deposit = &requests[0];
+
+        assert_eq!(deposit.request_type, DEPOSIT_REQUEST_TYPE);
+        assert_eq!(deposit.block_number, 22_431_084);
+        assert_eq!(deposit.block_hash, BLOCK_HASH);
+        assert_eq!(deposit.pubkey, hex_of(0xa1, PUBKEY_SIZE));
+        assert_eq!(
+            deposit.withdrawal_credentials.as_deref(),
+            Some("0x010000000000000000000000d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1d1")
+        );
+        assert_eq!(deposit.amount, Some(32_000_000_000));
+        assert_eq!(deposit.signature, hex_of(0xb2, 96));
+        assert_eq!(deposit.deposit_index, Some(1_900_000));
+
+        let truncated = BlockRequestSources {
+            deposit_logs: vec![deposit_log(1)[..64].to_vec()],
+            ..Default::default()
+        };
+        assert!(block_requests(22_431_084, BLOCK_HASH, &truncated).is_empty());
+    }
+
+    #[test]
+    fn dequeues_the_parent_queue_before_the_block_logs() {
+        let requests = block_requests(22_431_084, BLOCK_HASH, &sources());
+        let withdrawals: Vec<_> = requests
+            .iter()
+            .filter(|request| request.request_type == WITHDRAWAL_REQUEST_TYPE)
+            .collect();
+
+        let source_addresses: Vec<_> = withdrawals.iter().map(|request| request.source_address.clone()).collect();
+        assert_eq!(source_addresses, [hex_of(0x11, 20), hex_of(0x12, 20), hex_of(0x13, 20)]);
+        let pubkeys: Vec<_> = withdrawals.iter().map(|request| request.pubkey.clone()).collect();
+        assert_eq!(pubkeys, [0xc1, 0xc2, 0xc3].map(|byte| hex_of(byte, PUBKEY_SIZE)));
+        // Little-endian from the system call, big-endian from the logs
+        let amounts: Vec<_> = withdrawals.iter().map(|request| request.amount).collect();
+        assert_eq!(amounts, [Some(0), Some(1_000_000_000), Some(2_000_000_000)]);
+        let indexes: Vec<_> = withdrawals.iter().map(|request| request.request_index).collect();
+        assert_eq!(indexes, [0, 1, 2]);
+    }
+
+    #[test]
+    fn caps_the_requests_dequeued_per_block() {
+        let requests = block_requests(
+            22_431_084,
+            BLOCK_HASH,
+            &BlockRequestSources {
+                pending_withdrawal_requests: (0..20)
+                    .flat_map(|i| withdrawal_request(i, 0xc1, 1u64.to_le_bytes()))
+                    .collect(),
+                withdrawal_request_logs: vec![withdrawal_request(0x99, 0xc3, 1u64.to_be_bytes())],
+                ..sources()
+            },
+        );
+
+        let withdrawals: Vec<_> = requests
+            .iter()
+            .filter(|request| request.request_type == WITHDRAWAL_REQUEST_TYPE)
+            .collect();
+        assert_eq!(withdrawals.len(), MAX_WITHDRAWAL_REQUESTS_PER_BLOCK);
+        assert_eq!(withdrawals[15].source_address, hex_of(15, 20));
+        assert_eq!(withdrawals[15].request_index, 15);
+
+        let consolidations: Vec<_> = requests
+            .iter()
+            .filter(|request| request.request_type == CONSOLIDATION_REQUEST_TYPE)
+            .collect();
+        assert_eq!(consolidations.len(), MAX_CONSOLIDATION_REQUESTS_PER_BLOCK);
+        assert_eq!(consolidations[0].source_address, hex_of(0x21, 20));
+        assert_eq!(consolidations[0].target_pubkey, hex_of(0xe2, PUBKEY_SIZE));
+        assert_eq!(consolidations[1].source_address, hex_of(0x22, 20));
+        assert_eq!(consolidations[1].request_index, 1);
+    }
+
+    #[test]
+    fn hashes_the_hash_of_each_request_type() {
+        let requests = block_requests(22_431_084, BLOCK_HASH, &sources());
+        let withdrawals: Vec<_> = requests
+            .iter()
+            .filter(|request| request.request_type == WITHDRAWAL_REQUEST_TYPE)
+            .cloned()
+            .collect();
+
+        assert_eq!(
+            requests_hash(&requests),
+            Some("0x7c446697689463b5cf7ffedfa1751adfd20886363abe463cfe99dc308bbc35a9".parse().unwrap())
+        );
+        // Types without requests are left out
+        assert_eq!(
+            requests_hash(&withdrawals),
+            Some("0x1f5c7d7b7adb2bed36370b608d2c43848c9462195b2953e8a97f023f3b49dd21".parse().unwrap())
+        );
+        // The requests hash of every Prague block without requests
+        assert_eq!(
+            requests_hash(&[]),
+            Some("0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn cannot_hash_an_incomplete_request() {
+        let mut requests = block_requests(22_431_084, BLOCK_HASH, &sources());
+        requests[0].signature = None;
+
+        assert_eq!(requests_hash(&requests), None);
+    }
+}
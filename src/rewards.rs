@@ -0,0 +1,186 @@
+use crate::chain_config::ChainConfig;
+use crate::domain::{Block, BlockReward, Uncle, UncleReward};
+use ethers::types::U256;
+use ethers::utils::WEI_IN_ETHER;
+
+/// The proof-of-work block reward in wei: 5 ETH at launch, 3 ETH from Byzantium (EIP-649),
+/// 2 ETH from Constantinople (EIP-1234) and nothing after the merge. The genesis block is not
+/// rewarded.
+pub fn static_block_reward(config: &ChainConfig, block_number: u64) -> U256 {
+    let ether = if block_number == 0 || config.is_merged(block_number) {
+        0
+    } else if config.is_constantinople(block_number) {
+        2
+    } else if config.is_byzantium(block_number) {
+        3
+    } else {
+        5
+    };
+    WEI_IN_ETHER * ether
+}
+
+/// The ETH a block mints: its static reward, the uncle inclusion rewards and the uncle rewards.
+pub fn block_issuance(block_number: u64, uncles: &[Uncle], config: &ChainConfig) -> U256 {
+    let static_reward = static_block_reward(config, block_number);
+    uncles.iter().fold(static_reward, |issuance, uncle| {
+        issuance + static_reward / 32 + uncle_reward(static_reward, uncle.block.number, block_number)
+    })
+}
+
+fn uncle_reward(static_reward: U256, uncle_number: u64, block_number: u64) -> U256 {
+    let distance_factor = (uncle_number + 8).saturating_sub(block_number);
+    static_reward * distance_factor / 8
+}
+
+/// What a block paid its producer and the miners of its uncles, and what it burnt.
+///
+/// The producer earns the static reward plus 1/32 of it per included uncle, plus the priority
+/// fees of the transactions. An uncle miner earns `(uncle_number + 8 - block_number) / 8` of the
+/// static reward. `total_priority_fees` and `total_reward` need the transaction fees computed from
+/// receipts and are `None` otherwise; `burnt_fees` is `None` before London.
+pub fn calculate_block_reward(block: &Block, uncles: &[Uncle], config: &ChainConfig) -> BlockReward {
+    let static_reward = static_block_reward(config, block.number);
+    let uncle_inclusion_reward = static_reward / 32 * uncles.len();
+
+    let uncle_rewards = uncles
+        .iter()
+        .map(|uncle| UncleReward {
+            uncle_index: uncle.uncle_index,
+            miner: uncle.block.miner.clone(),
+            reward: uncle_reward(static_reward, uncle.block.number, block.number).to_string(),
+        })
+        .collect();
+
+    let total_priority_fees = block
+        .transactions
+        .iter()
+        .map(|transaction| U256::from_dec_str(transaction.priority_fee_paid.as_deref()?).ok())
+        .try_fold(U256::zero(), |total, fee| Some(total.saturating_add(fee?)));
+    let burnt_fees = block
+        .base_fee_per_gas
+        .map(|base_fee_per_gas| U256::from(base_fee_per_gas) * block.gas_used);
+
+    let total_reward = total_priority_fees
+        .map(|fees| static_reward.saturating_add(uncle_inclusion_reward).saturating_add(fees));
+
+    BlockReward {
+        block_number: block.number,
+        block_hash: block.hash.clone(),
+        miner: block.miner.clone(),
+        static_reward: static_reward.to_string(),
+        uncle_inclusion_reward: uncle_inclusion_reward.to_string(),
+        uncle_rewards,
+        total_priority_fees: total_priority_fees.map(|fees| fees.to_string()),
+        burnt_fees: burnt_fees.map(|fees| fees.to_string()),
+        total_reward: total_reward.map(|reward| reward.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ETHER: &str = "000000000000000000";
+
+    fn header(number: u64, miner: &str, base_fee_per_gas: Option<u64>, gas_used: u64) -> serde_json::Value {
+        json!({
+            "number": number, "hash": format!("0x{:064x}", number), "parent_hash": "0x", "nonce": "0x",
+            "sha3_uncles": "0x", "logs_bloom": "0x", "transactions_root": "0x", "state_root": "0x",
+            "receipts_root": "0x", "miner": miner, "difficulty": "0", "total_difficulty": null, "size": 0,
+            "extra_data": "0x", "gas_limit": 30_000_000, "gas_used": gas_used, "timestamp": 0,
+            "transaction_count": 0, "base_fee_per_gas": base_fee_per_gas,
+        })
+    }
+
+    fn block(number: u64, base_fee_per_gas: Option<u64>, gas_used: u64, priority_fees: &[Option<&str>]) -> Block {
+        let mut block = header(number, "0xea674fdde714fd979de3edf0f56aa9716b898ec8", base_fee_per_gas, gas_used);
+        block["transactions"] = priority_fees
+            .iter()
+            .enumerate()
+            .map(|(index, priority_fee_paid)| {
+                json!({
+                    "hash": format!("0x{:064x}", index), "nonce": 0, "block_hash": null, "block_number": number,
+                    "transaction_index": index, "from_address": "0x", "to_address": null, "value": "0",
+                    "gas": 21000, "gas_price": "0", "input": "0x", "block_timestamp": 0,
+                    "priority_fee_paid": priority_fee_paid,
+                })
+            })
+            .collect();
+        serde_json::from_value(block).unwrap()
+    }
+
+    fn uncle(number: u64, including_block_number: u64, uncle_index: u64) -> Uncle {
+        let mut uncle = header(number, "0x52bc44d5378309ee2abf1539bf71de1b7d7be3b5", None, 0);
+        uncle["including_block_number"] = including_block_number.into();
+        uncle["including_block_hash"] = format!("0x{:064x}", including_block_number).into();
+        uncle["uncle_index"] = uncle_index.into();
+        serde_json::from_value(uncle).unwrap()
+    }
+
+    #[test]
+    fn pays_the_static_reward_of_each_fork() {
+        let config = ChainConfig::mainnet();
+        let reward = |block_number| static_block_reward(&config, block_number).to_string();
+
+        assert_eq!(reward(0), "0");
+        assert_eq!(reward(1), format!("5{}", ETHER));
+        assert_eq!(reward(4_369_999), format!("5{}", ETHER));
+        assert_eq!(reward(4_370_000), format!("3{}", ETHER));
+        assert_eq!(reward(7_279_999), format!("3{}", ETHER));
+        assert_eq!(reward(7_280_000), format!("2{}", ETHER));
+        assert_eq!(reward(15_537_393), format!("2{}", ETHER));
+        assert_eq!(reward(15_537_394), "0");
+    }
+
+    #[test]
+    fn rewards_uncles_by_their_distance() {
+        let block = block(7_280_010, None, 0, &[]);
+        let uncles = [uncle(7_280_009, 7_280_010, 0), uncle(7_280_004, 7_280_010, 1)];
+
+        let reward = calculate_block_reward(&block, &uncles, &ChainConfig::mainnet());
+
+        assert_eq!(reward.static_reward, "2000000000000000000");
+        assert_eq!(reward.uncle_inclusion_reward, "125000000000000000");
+        assert_eq!(reward.uncle_rewards.len(), 2);
+        assert_eq!(reward.uncle_rewards[0].reward, "1750000000000000000");
+        assert_eq!(reward.uncle_rewards[1].uncle_index, 1);
+        assert_eq!(reward.uncle_rewards[1].miner, "0x52bc44d5378309ee2abf1539bf71de1b7d7be3b5");
+        assert_eq!(reward.uncle_rewards[1].reward, "500000000000000000");
+        assert_eq!(reward.burnt_fees, None);
+        assert_eq!(reward.total_reward.as_deref(), Some("2125000000000000000"));
+        assert_eq!(
+            block_issuance(block.number, &uncles, &ChainConfig::mainnet()).to_string(),
+            "4375000000000000000"
+        );
+    }
+
+    #[test]
+    fn adds_priority_fees_and_burns_the_base_fee_after_london() {
+        // The first London block burnt its 1 gwei base fee on 30,025,257 gas
+        let block = block(
+            12_965_000,
+            Some(1_000_000_000),
+            30_025_257,
+            &[Some("21000000000000"), Some("1500000000000000")],
+        );
+
+        let reward = calculate_block_reward(&block, &[], &ChainConfig::mainnet());
+
+        assert_eq!(reward.burnt_fees.as_deref(), Some("30025257000000000"));
+        assert_eq!(reward.total_priority_fees.as_deref(), Some("1521000000000000"));
+        assert_eq!(reward.total_reward.as_deref(), Some("2001521000000000000"));
+    }
+
+    #[test]
+    fn leaves_fee_totals_empty_without_every_priority_fee() {
+        let block = block(17_000_000, Some(30_000_000_000), 21_000, &[Some("21000000000000"), None]);
+
+        let reward = calculate_block_reward(&block, &[], &ChainConfig::mainnet());
+
+        assert_eq!(reward.static_reward, "0");
+        assert_eq!(reward.total_priority_fees, None);
+        assert_eq!(reward.total_reward, None);
+        assert_eq!(reward.burnt_fees.as_deref(), Some("630000000000000"));
+    }
+}
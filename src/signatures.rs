@@ -0,0 +1,403 @@
+use ethers::types::{
+    Address, OtherFields, Signature, SignatureError, Transaction as EthersTransaction, H256, U256, U64,
+};
+use ethers::utils::keccak256;
+use ethers::utils::rlp::RlpStream;
+use serde::Deserialize;
+
+/// Prefix of the EIP-7702 authorization signing payload.
+const SET_CODE_AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// An `authorizationList` entry as returned by the node. Some clients name the parity `v`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedAuthorization {
+    pub chain_id: U256,
+    pub address: Address,
+    pub nonce: U64,
+    #[serde(alias = "v")]
+    pub y_parity: U64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl SignedAuthorization {
+    /// Recovers the account that signed the authorization. The signature covers
+    /// `keccak256(0x05 || rlp([chain_id, address, nonce]))`; invalid signatures recover nothing.
+    pub fn recover_authority(&self) -> Option<Address> {
+        let mut stream = RlpStream::new_list(3);
+        stream.append(&self.chain_id);
+        stream.append(&self.address);
+        stream.append(&self.nonce);
+
+        let payload = [&[SET_CODE_AUTHORIZATION_MAGIC][..], &stream.out()].concat();
+        let signature = Signature {
+            r: self.r,
+            s: self.s,
+            v: self.y_parity.as_u64(),
+        };
+        signature.recover(H256::from(keccak256(payload))).ok()
+    }
+}
+
+/// Reads the `authorizationList` of a set-code transaction from the fields ethers does not model.
+pub fn authorization_list(other: &OtherFields) -> Option<Vec<SignedAuthorization>> {
+    other.get_deserialized("authorizationList")?.ok()
+}
+
+/// The chain id encoded in the `v` of an EIP-155 legacy signature.
+pub fn legacy_chain_id(v: u64) -> Option<u64> {
+    (v >= 35).then(|| (v - 35) / 2)
+}
+
+/// Recomputes the hash the sender signed and recovers the sender from the signature.
+///
+/// Returns `Ok(None)` for transaction types without a known signing scheme, such as L2 deposit
+/// transactions, and an error when the signature does not recover to any address.
+pub fn recover_sender(tx: &EthersTransaction) -> Result<Option<Address>, SignatureError> {
+    let Some(signing_hash) = transaction_signing_hash(tx) else {
+        return Ok(None);
+    };
+
+    let signature = Signature {
+        r: tx.r,
+        s: tx.s,
+        v: tx.v.as_u64(),
+    };
+    signature.recover(signing_hash).map(Some)
+}
+
+/// The hash signed by the sender, following the payload layout of each transaction type:
+/// legacy (with or without EIP-155 replay protection), EIP-2930, EIP-1559, EIP-4844 and EIP-7702.
+fn transaction_signing_hash(tx: &EthersTransaction) -> Option<H256> {
+    let transaction_type = tx.transaction_type.map_or(0, |transaction_type| transaction_type.as_u64());
+    let chain_id = tx.chain_id.unwrap_or_default();
+    let access_list = tx.access_list.clone().unwrap_or_default();
+
+    let mut stream = RlpStream::new();
+    match transaction_type {
+        0 => {
+            let legacy_chain_id = legacy_chain_id(tx.v.as_u64());
+            stream.begin_list(if legacy_chain_id.is_some() { 9 } else { 6 });
+            stream.append(&tx.nonce);
+            stream.append(&tx.gas_price?);
+            stream.append(&tx.gas);
+            append_to(&mut stream, tx.to);
+            stream.append(&tx.value);
+            stream.append(&tx.input.to_vec());
+            if let Some(legacy_chain_id) = legacy_chain_id {
+                stream.append(&legacy_chain_id);
+                stream.append(&0u8);
+                stream.append(&0u8);
+            }
+            return Some(H256::from(keccak256(stream.out())));
+        }
+        1 => {
+            stream.begin_list(8);
+            stream.append(&chain_id);
+            stream.append(&tx.nonce);
+            stream.append(&tx.gas_price?);
+            stream.append(&tx.gas);
+            append_to(&mut stream, tx.to);
+            stream.append(&tx.value);
+            stream.append(&tx.input.to_vec());
+            stream.append(&access_list);
+        }
+        2..=4 => {
+            let fields_count = match transaction_type {
+                2 => 9,
+                3 => 11,
+                _ => 10,
+            };
+            stream.begin_list(fields_count);
+            stream.append(&chain_id);
+            stream.append(&tx.nonce);
+            stream.append(&tx.max_priority_fee_per_gas?);
+            stream.append(&tx.max_fee_per_gas?);
+            stream.append(&tx.gas);
+            append_to(&mut stream, tx.to);
+            stream.append(&tx.value);
+            stream.append(&tx.input.to_vec());
+            stream.append(&access_list);
+
+            if transaction_type == 3 {
+                let max_fee_per_blob_gas: U256 = tx.other.get_deserialized("maxFeePerBlobGas")?.ok()?;
+                let blob_versioned_hashes: Vec<H256> = tx.other.get_deserialized("blobVersionedHashes")?.ok()?;
+                stream.append(&max_fee_per_blob_gas);
+                stream.append_list(&blob_versioned_hashes);
+            } else if transaction_type == 4 {
+                let authorizations = authorization_list(&tx.other)?;
+                stream.begin_list(authorizations.len());
+                for authorization in &authorizations {
+                    stream.begin_list(6);
+                    stream.append(&authorization.chain_id);
+                    stream.append(&authorization.address);
+                    stream.append(&authorization.nonce);
+                    stream.append(&authorization.y_parity);
+                    stream.append(&authorization.r);
+                    stream.append(&authorization.s);
+                }
+            }
+        }
+        _ => return None,
+    }
+
+    let payload = [&[transaction_type as u8][..], &stream.out()].concat();
+    Some(H256::from(keccak256(payload)))
+}
+
+/// Contract creations have no recipient, which is encoded as an empty string.
+fn append_to(stream: &mut RlpStream, to: Option<Address>) {
+    match to {
+        Some(to) => stream.append(&to),
+        None => stream.append(&""),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mappers::{
+        enrich_transaction_with_recovered_sender, ethers_transaction_to_transaction,
+    };
+    use serde_json::json;
+
+    /// Account #0 of the anvil and hardhat development mnemonic, which signs the vectors below.
+    const DEV_ACCOUNT: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    /// A mainnet delegation of the dev account, for its nonce 9.
+    fn authorization() -> serde_json::Value {
+        json!({
+            "chainId": "0x1",
+            "address": "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b",
+            "nonce": "0x9",
+            "yParity": "0x0",
+            "r": "0xe17ef06698ea3d3ab3fe7028217e440257c025b324b6e959f14d15fada77c614",
+            "s": "0x1ad251c0d64a2d03d2e8a75099a1966a8a27eef85b1e36be8b3a0a3c505205ad",
+        })
+    }
+
+    fn address(value: &str) -> Address {
+        value.parse().unwrap()
+    }
+
+    fn hash(value: &str) -> H256 {
+        value.parse().unwrap()
+    }
+
+    fn transaction(value: serde_json::Value) -> EthersTransaction {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Mainnet transaction 0x280cde7c…, a legacy Uniswap swap with EIP-155 replay protection.
+    fn legacy_transaction() -> serde_json::Value {
+        json!({
+            "hash": "0x280cde7cdefe4b188750e76c888f13bd05ce9a4d7767730feefe8a0e50ca6fc4",
+            "nonce": "0x78b",
+            "from": "0xa12e1462d0ced572f396f58b6e2d03894cd7c8a4",
+            "to": "0x7a250d5630b4cf539739df2c5dacb4c659f2488d",
+            "value": "0xc46549a521b13d8",
+            "gasPrice": "0x5d21dba00",
+            "gas": "0x22ef1",
+            "input": "0x7ff36ab50000000000000000000000000000000000000000000066ab5a608bd00a23f2fe000000000000000000000000000000000000000000000000000000000000008000000000000000000000000048c04ed5691981c42154c6167398f95e8f38a7ff00000000000000000000000000000000000000000000000000000000632ceac70000000000000000000000000000000000000000000000000000000000000002000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20000000000000000000000006c6ee5e31d828de241282b9606c8e98ea48526e2",
+            "v": "0x25",
+            "r": "0xc9077369501641a92ef7399ff81c21639ed4fd8fc69cb793cfa1dbfab342e10a",
+            "s": "0x615facb2f1bcf3274a354cfe384a38d0cc008a11c2dd23a69111bc6930ba27a8",
+        })
+    }
+
+    /// Mainnet transaction 0x6d38fc8a…, an EIP-2930 transaction.
+    fn access_list_transaction() -> serde_json::Value {
+        json!({
+            "hash": "0x6d38fc8aee934858815ed41273cece3b676c368e9c6e39f172313a0685e1f175",
+            "type": "0x1",
+            "chainId": "0x1",
+            "nonce": "0x34c",
+            "from": "0x2360f8fedf7d0e2a121fae5bd83e8ff55e8e9745",
+            "to": "0x0087bb802d9c0e343f00510000729031ce00bf27",
+            "value": "0x0",
+            "gasPrice": "0x3d9f1b8815",
+            "gas": "0x7a120",
+            "input": "0x1e1326a300000000000000000000000088e6a0c2ddd26feeb64f039a2c41296fcb3f56400000000000000000000000000000000000000000000000000000001d3b3e730000000000000000000000000000000000000000000000000596b93e53696740000000000000000000000000000000000000000000000000000000000000000001",
+            "accessList": [],
+            "v": "0x1",
+            "r": "0xbbfd754ed51b34d0a8577f69b4c42ce6b47fee6ecf49114bb135e7e8eadbb336",
+            "s": "0x433692134eb7e7686e9aefafa9f69c601aa977c00cc85c827782f5fb1f1cff0f",
+        })
+    }
+
+    /// Mainnet transaction 0x781d5764…, an EIP-1559 WETH deposit.
+    fn dynamic_fee_transaction() -> serde_json::Value {
+        json!({
+            "hash": "0x781d57642f4e3277fe01d370bd45ba1361b475bea6a35f26814e02a0a2b26549",
+            "type": "0x2",
+            "chainId": "0x1",
+            "nonce": "0x1df",
+            "from": "0x057f8d0f6fb2703197363f75c002f766f1c4287a",
+            "to": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "value": "0x2b40d6d551c8970c",
+            "maxPriorityFeePerGas": "0x1344ead983",
+            "maxFeePerGas": "0x1344ead983",
+            "gas": "0x6d22",
+            "input": "0xd0e30db0",
+            "accessList": [],
+            "v": "0x1",
+            "r": "0x5616cdaec839ca14d209b59eafb706e623169dc9d0fa58fbf13931cef5b5e3b0",
+            "s": "0x3e708f8044bd158d29c2e250b6a98ea637c3bc460beeea63a8f00f7cebac432a",
+        })
+    }
+
+    /// An EIP-4844 transaction of the dev account with two blobs.
+    fn blob_transaction() -> serde_json::Value {
+        json!({
+            "hash": "0xb00ab84df08898e8945bce1aaf395402e1072e4254770411993df038052a5825",
+            "type": "0x3",
+            "chainId": "0x1",
+            "nonce": "0x7",
+            "from": DEV_ACCOUNT,
+            "to": "0xff00000000000000000000000000000000000010",
+            "value": "0x0",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "maxFeePerGas": "0x6fc23ac00",
+            "maxFeePerBlobGas": "0x1bf08eb000",
+            "blobVersionedHashes": [
+                "0x015b756bff8cac283a13cfed3bb3db748d9cca53291eccff357397bd487b0c28",
+                "0x015b3e6f6c92105eadf6d633bb0b56bd57e22b5dd27d5da76c6c8ee7e63e2f9e",
+            ],
+            "gas": "0x5208",
+            "input": "0x",
+            "accessList": [],
+            "v": "0x0",
+            "yParity": "0x0",
+            "r": "0x10db3a7ee297176cdb13047d2626de02fd692f59616cd799e5424f8b85322384",
+            "s": "0x69bc84bb8e85c7f58c1180f9ca5ef86a0a6175e8e854991867dad4adc7a5655f",
+        })
+    }
+
+    /// An EIP-7702 transaction in which the dev account sends its own delegation.
+    fn set_code_transaction() -> serde_json::Value {
+        json!({
+            "hash": "0xf3887c21e5a86cf8e8db66386537b64e40cdcefd0ec174ab8b6f9fe5cdf05e5e",
+            "type": "0x4",
+            "chainId": "0x1",
+            "nonce": "0x8",
+            "from": DEV_ACCOUNT,
+            "to": DEV_ACCOUNT,
+            "value": "0x0",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "maxFeePerGas": "0x6fc23ac00",
+            "gas": "0x186a0",
+            "input": "0x",
+            "accessList": [],
+            "authorizationList": [authorization()],
+            "v": "0x0",
+            "yParity": "0x0",
+            "r": "0x16a8fcfe6bd614ba09324064e5ed985a6399c3ee0799f3a44f5e34775acf5b14",
+            "s": "0x1de34f1ebae1ab91dbac615d04e21e69ba15906bc79a6580214f0e80662f96f4",
+        })
+    }
+
+    /// Checks the signing hash of a transaction and that its signature recovers the node's `from`.
+    fn assert_signed_by_sender(value: serde_json::Value, signing_hash: &str) {
+        let tx = transaction(value);
+
+        assert_eq!(transaction_signing_hash(&tx), Some(hash(signing_hash)));
+        assert_eq!(recover_sender(&tx).unwrap(), Some(tx.from));
+    }
+
+    /// The mismatch flag of a transaction, as set when exporting with sender verification.
+    fn from_address_mismatch(value: serde_json::Value) -> Option<bool> {
+        let tx = transaction(value);
+        let recovered_sender = recover_sender(&tx);
+        let mut transaction = ethers_transaction_to_transaction(tx, 0);
+        enrich_transaction_with_recovered_sender(&mut transaction, recovered_sender);
+        transaction.from_address_mismatch
+    }
+
+    #[test]
+    fn recovers_the_authority_of_an_authorization() {
+        let authorization: SignedAuthorization = serde_json::from_value(authorization()).unwrap();
+
+        assert_eq!(authorization.recover_authority(), Some(address(DEV_ACCOUNT)));
+    }
+
+    #[test]
+    fn reads_the_parity_of_an_authorization_from_v() {
+        let mut value = authorization();
+        value["v"] = value["yParity"].take();
+        value.as_object_mut().unwrap().remove("yParity");
+        let authorization: SignedAuthorization = serde_json::from_value(value).unwrap();
+
+        assert_eq!(authorization.recover_authority(), Some(address(DEV_ACCOUNT)));
+    }
+
+    #[test]
+    fn recovers_another_authority_from_a_changed_authorization() {
+        let mut value = authorization();
+        value["nonce"] = json!("0xa");
+        let authorization: SignedAuthorization = serde_json::from_value(value).unwrap();
+
+        assert_ne!(authorization.recover_authority(), Some(address(DEV_ACCOUNT)));
+    }
+
+    #[test]
+    fn recovers_the_sender_of_a_legacy_transaction() {
+        assert_eq!(legacy_chain_id(0x25), Some(1));
+        assert_signed_by_sender(
+            legacy_transaction(),
+            "0x379ff32b417de419215242f8c5c2f7fe533948b45f0dbe842f7300f889b263ef",
+        );
+    }
+
+    #[test]
+    fn recovers_the_sender_of_an_access_list_transaction() {
+        assert_signed_by_sender(
+            access_list_transaction(),
+            "0x06ad0de969ee4e9f9940a5052540550f74bb3bf967c7cf7664640a684c0d2409",
+        );
+    }
+
+    #[test]
+    fn recovers_the_sender_of_a_dynamic_fee_transaction() {
+        assert_signed_by_sender(
+            dynamic_fee_transaction(),
+            "0xa42b89d63f75e5e05f9f6edcb4f8aca4a4a242c118d2c31e23e458bcbaad1df0",
+        );
+    }
+
+    #[test]
+    fn recovers_the_sender_of_a_blob_transaction() {
+        assert_signed_by_sender(
+            blob_transaction(),
+            "0xaab09791c445874ffd91fc97874567c5c50208bf060dd6f4d2918b04c27a4c3f",
+        );
+    }
+
+    #[test]
+    fn recovers_the_sender_of_a_set_code_transaction() {
+        assert_signed_by_sender(
+            set_code_transaction(),
+            "0xdaa0d0c00f7ff029e88150e3fe1bbfb89ace867698a9608138f698cc0cba7c2c",
+        );
+    }
+
+    #[test]
+    fn skips_unknown_transaction_types() {
+        let mut value = dynamic_fee_transaction();
+        value["type"] = json!("0x7e");
+
+        assert_eq!(recover_sender(&transaction(value)).unwrap(), None);
+    }
+
+    #[test]
+    fn flags_a_tampered_signature() {
+        let mut tampered_s = dynamic_fee_transaction();
+        tampered_s["s"] = json!("0x3e708f8044bd158d29c2e250b6a98ea637c3bc460beeea63a8f00f7cebac432b");
+        let mut tampered_r = legacy_transaction();
+        tampered_r["r"] = json!("0x0");
+
+        assert_eq!(from_address_mismatch(dynamic_fee_transaction()), Some(false));
+        assert_eq!(from_address_mismatch(tampered_s), Some(true));
+        assert_eq!(from_address_mismatch(tampered_r), Some(true));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::domain::{BalanceChange, StorageChange};
+use ethers::types::{AccountDiff, Address, Bytes, Diff, DiffMode, StateDiff, H256, U256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// One entry of a `debug_traceBlockByNumber` response with the `prestateTracer` in diff mode.
+/// Older Geth versions omit `txHash`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GethTransactionStateDiff {
+    #[serde(rename = "txHash", default)]
+    pub tx_hash: Option<H256>,
+    pub result: DiffMode,
+}
+
+/// Identifies the transaction the changes of a state diff belong to.
+pub struct TransactionRef<'a> {
+    pub block_number: u64,
+    pub transaction_hash: &'a str,
+    pub transaction_index: u64,
+}
+
+/// Maps a `stateDiff` from `trace_replayBlockTransactions`. Only fields that changed are set
+/// on the balance change rows; accounts whose storage alone changed only get storage rows.
+pub fn parity_state_diff_to_changes(
+    state_diff: &StateDiff,
+    transaction: &TransactionRef,
+) -> (Vec<BalanceChange>, Vec<StorageChange>) {
+    let mut balance_changes = vec![];
+    let mut storage_changes = vec![];
+
+    for (address, account_diff) in &state_diff.0 {
+        let AccountDiff {
+            balance,
+            nonce,
+            code,
+            storage,
+        } = account_diff;
+
+        let balance = diff_values(balance, U256::zero());
+        let nonce = diff_values(nonce, U256::zero());
+        let code = diff_values(code, Bytes::new());
+        if let Some(balance_change) = balance_change(transaction, address, balance, nonce, code) {
+            balance_changes.push(balance_change);
+        }
+
+        for (slot, diff) in storage {
+            if let Some((before, after)) = diff_values(diff, H256::zero()) {
+                storage_changes.push(storage_change(transaction, address, slot, before, after));
+            }
+        }
+    }
+
+    (balance_changes, storage_changes)
+}
+
+/// Maps the `prestateTracer` diff mode output. `pre` holds the state of every modified account
+/// before the transaction and `post` only the fields that changed; accounts missing from `post`
+/// were self-destructed and storage slots missing from `post` were cleared.
+pub fn geth_state_diff_to_changes(
+    diff: &DiffMode,
+    transaction: &TransactionRef,
+) -> (Vec<BalanceChange>, Vec<StorageChange>) {
+    let mut balance_changes = vec![];
+    let mut storage_changes = vec![];
+
+    let addresses = diff.pre.keys().chain(diff.post.keys()).collect::<BTreeSet<_>>();
+    for address in addresses {
+        let pre = diff.pre.get(address).cloned().unwrap_or_default();
+        let post = diff.post.get(address);
+        let deleted = post.is_none();
+        let post = post.cloned().unwrap_or_default();
+
+        let pre_code = pre.code.as_deref().map(decode_code).unwrap_or_default();
+        let post_code = post.code.as_deref().map(decode_code);
+
+        let balance = post_value(pre.balance.unwrap_or_default(), post.balance, deleted, U256::zero());
+        let nonce = post_value(pre.nonce.unwrap_or_default(), post.nonce, deleted, U256::zero());
+        let code = post_value(pre_code, post_code, deleted, Bytes::new());
+        if let Some(balance_change) = balance_change(transaction, address, balance, nonce, code) {
+            balance_changes.push(balance_change);
+        }
+
+        let pre_storage = pre.storage.unwrap_or_default();
+        let post_storage = post.storage.unwrap_or_default();
+        let slots = pre_storage.keys().chain(post_storage.keys()).collect::<BTreeSet<_>>();
+        for slot in slots {
+            let before = pre_storage.get(slot).copied().unwrap_or_default();
+            let after = post_storage.get(slot).copied().unwrap_or_default();
+            if before != after {
+                storage_changes.push(storage_change(transaction, address, slot, before, after));
+            }
+        }
+    }
+
+    (balance_changes, storage_changes)
+}
+
+fn diff_values<T: Clone>(diff: &Diff<T>, zero: T) -> Option<(T, T)> {
+    match diff {
+        Diff::Same => None,
+        Diff::Born(value) => Some((zero, value.clone())),
+        Diff::Died(value) => Some((value.clone(), zero)),
+        Diff::Changed(changed) => Some((changed.from.clone(), changed.to.clone())),
+    }
+}
+
+fn post_value<T: PartialEq>(pre: T, post: Option<T>, deleted: bool, zero: T) -> Option<(T, T)> {
+    let after = match post {
+        Some(after) => after,
+        None if deleted => zero,
+        None => return None,
+    };
+
+    (pre != after).then_some((pre, after))
+}
+
+fn decode_code(code: &str) -> Bytes {
+    hex::decode(code.trim_start_matches("0x"))
+        .unwrap_or_default()
+        .into()
+}
+
+fn balance_change(
+    transaction: &TransactionRef,
+    address: &Address,
+    balance: Option<(U256, U256)>,
+    nonce: Option<(U256, U256)>,
+    code: Option<(Bytes, Bytes)>,
+) -> Option<BalanceChange> {
+    if balance.is_none() && nonce.is_none() && code.is_none() {
+        return None;
+    }
+
+    let code_hash = |code: &Bytes| format!("0x{}", hex::encode(keccak256(code)));
+
+    Some(BalanceChange {
+        block_number: transaction.block_number,
+        transaction_hash: transaction.transaction_hash.to_string(),
+        transaction_index: transaction.transaction_index,
+        address: format!("{:#x}", address),
+        balance_before: balance.as_ref().map(|(before, _)| before.to_string()),
+        balance_after: balance.as_ref().map(|(_, after)| after.to_string()),
+        nonce_before: nonce.map(|(before, _)| before.as_u64()),
+        nonce_after: nonce.map(|(_, after)| after.as_u64()),
+        code_hash_before: code.as_ref().map(|(before, _)| code_hash(before)),
+        code_hash_after: code.as_ref().map(|(_, after)| code_hash(after)),
+    })
+}
+
+fn storage_change(
+    transaction: &TransactionRef,
+    address: &Address,
+    slot: &H256,
+    before: H256,
+    after: H256,
+) -> StorageChange {
+    StorageChange {
+        block_number: transaction.block_number,
+        transaction_hash: transaction.transaction_hash.to_string(),
+        transaction_index: transaction.transaction_index,
+        address: format!("{:#x}", address),
+        slot: format!("{:#x}", slot),
+        value_before: format!("{:#x}", before),
+        value_after: format!("{:#x}", after),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TRANSACTION: TransactionRef<'static> = TransactionRef {
+        block_number: 19_000_000,
+        transaction_hash: "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060",
+        transaction_index: 4,
+    };
+    /// keccak256 of empty code
+    const EMPTY_CODE_HASH: &str = "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+
+    fn address(last_byte: u8) -> String {
+        format!("0x{:040x}", last_byte)
+    }
+
+    fn word(value: u8) -> String {
+        format!("0x{:064x}", value)
+    }
+
+    fn code_hash(code: &[u8]) -> Option<String> {
+        Some(format!("0x{}", hex::encode(keccak256(code))))
+    }
+
+    type BalanceRow = (String, Option<String>, Option<String>, Option<u64>, Option<u64>, Option<String>, Option<String>);
+
+    fn balance_rows(balance_changes: &[BalanceChange]) -> Vec<BalanceRow> {
+        balance_changes
+            .iter()
+            .map(|change| {
+                (
+                    change.address.clone(),
+                    change.balance_before.clone(),
+                    change.balance_after.clone(),
+                    change.nonce_before,
+                    change.nonce_after,
+                    change.code_hash_before.clone(),
+                    change.code_hash_after.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn storage_rows(storage_changes: &[StorageChange]) -> Vec<(String, String, String, String)> {
+        storage_changes
+            .iter()
+            .map(|change| {
+                (change.address.clone(), change.slot.clone(), change.value_before.clone(), change.value_after.clone())
+            })
+            .collect()
+    }
+
+    fn text(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn maps_parity_state_diffs() {
+        let state_diff: StateDiff = serde_json::from_value(json!({
+            address(1): {
+                "balance": {"*": {"from": "0xde0b6b3a7640000", "to": "0xc7d713b49da0000"}},
+                "nonce": {"*": {"from": "0x5", "to": "0x6"}},
+                "code": "=",
+                "storage": {},
+            },
+            address(2): {
+                "balance": "=",
+                "nonce": "=",
+                "code": "=",
+                "storage": {
+                    word(1): {"*": {"from": word(5), "to": word(6)}},
+                    word(2): {"-": word(7)},
+                },
+            },
+            address(4): {
+                "balance": {"+": "0x64"},
+                "nonce": {"+": "0x1"},
+                "code": {"+": "0x6080"},
+                "storage": {word(1): {"+": word(9)}},
+            },
+        }))
+        .unwrap();
+
+        let (balance_changes, storage_changes) = parity_state_diff_to_changes(&state_diff, &TRANSACTION);
+
+        assert_eq!(
+            balance_rows(&balance_changes),
+            [
+                (address(1), text("1000000000000000000"), text("900000000000000000"), Some(5), Some(6), None, None),
+                (address(4), text("0"), text("100"), Some(0), Some(1), text(EMPTY_CODE_HASH), code_hash(&[0x60, 0x80])),
+            ]
+        );
+        assert_eq!(
+            storage_rows(&storage_changes),
+            [
+                (address(2), word(1), word(5), word(6)),
+                (address(2), word(2), word(7), word(0)),
+                (address(4), word(1), word(0), word(9)),
+            ]
+        );
+        assert_eq!(balance_changes[0].block_number, 19_000_000);
+        assert_eq!(storage_changes[0].transaction_index, 4);
+    }
+
+    #[test]
+    fn maps_geth_prestate_diffs() {
+        let diff: DiffMode = serde_json::from_value(json!({
+            "pre": {
+                address(1): {"balance": "0xde0b6b3a7640000", "nonce": 5},
+                address(2): {"balance": "0x0", "code": "0x6080", "storage": {word(1): word(5), word(2): word(7)}},
+                address(3): {"balance": "0x2", "nonce": 1, "code": "0x60"},
+            },
+            "post": {
+                address(1): {"balance": "0xc7d713b49da0000", "nonce": 6},
+                address(2): {"storage": {word(1): word(6)}},
+                address(4): {"balance": "0x64", "nonce": 1, "code": "0x6080", "storage": {word(1): word(9)}},
+            },
+        }))
+        .unwrap();
+
+        let (balance_changes, storage_changes) = geth_state_diff_to_changes(&diff, &TRANSACTION);
+
+        // The account only in `pre` was deleted and the slot missing from `post` was cleared
+        assert_eq!(
+            balance_rows(&balance_changes),
+            [
+                (address(1), text("1000000000000000000"), text("900000000000000000"), Some(5), Some(6), None, None),
+                (address(3), text("2"), text("0"), Some(1), Some(0), code_hash(&[0x60]), text(EMPTY_CODE_HASH)),
+                (address(4), text("0"), text("100"), Some(0), Some(1), text(EMPTY_CODE_HASH), code_hash(&[0x60, 0x80])),
+            ]
+        );
+        assert_eq!(
+            storage_rows(&storage_changes),
+            [
+                (address(2), word(1), word(5), word(6)),
+                (address(2), word(2), word(7), word(0)),
+                (address(4), word(1), word(0), word(9)),
+            ]
+        );
+    }
+}
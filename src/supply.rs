@@ -0,0 +1,220 @@
+use crate::chain_config::ChainConfig;
+use crate::domain::{Block, EthSupply, Receipt, Uncle};
+use crate::rewards::block_issuance;
+use anyhow::{bail, Context, Result};
+use ethers::types::U256;
+use std::collections::HashMap;
+use std::path::Path;
+
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+/// What one block adds to and removes from the ETH supply, in wei.
+pub struct SupplyChanges {
+    pub block_number: u64,
+    pub block_hash: String,
+    /// Block and uncle rewards, which stop with the merge
+    pub issuance: U256,
+    /// The EIP-1559 base fee burn
+    pub burnt_fees: U256,
+    /// EIP-4895 withdrawals, which mint ETH moved from the beacon chain
+    pub withdrawals: U256,
+    /// The EIP-4844 blob gas fees, which are burnt in full
+    pub blob_fees_burnt: U256,
+}
+
+/// `receipts` only need to cover the blob transactions of the block.
+pub fn block_supply_changes(block: &Block, uncles: &[Uncle], receipts: &[Receipt], config: &ChainConfig) -> SupplyChanges {
+    let burnt_fees = block
+        .base_fee_per_gas
+        .map(|base_fee_per_gas| U256::from(base_fee_per_gas) * block.gas_used)
+        .unwrap_or_default();
+    let withdrawals = block
+        .withdrawals
+        .iter()
+        .fold(U256::zero(), |total, withdrawal| total + U256::from(withdrawal.amount) * WEI_PER_GWEI);
+    let blob_fees_burnt = receipts
+        .iter()
+        .filter_map(|receipt| Some(U256::from(receipt.blob_gas_used?) * receipt.blob_gas_price?))
+        .fold(U256::zero(), |total, fee| total + fee);
+
+    SupplyChanges {
+        block_number: block.number,
+        block_hash: block.hash.clone(),
+        issuance: block_issuance(block.number, uncles, config),
+        burnt_fees,
+        withdrawals,
+        blob_fees_burnt,
+    }
+}
+
+/// Applies the changes of the next block to the running supply and returns its row.
+pub fn apply_supply_changes(supply: &mut U256, changes: &SupplyChanges) -> Result<EthSupply> {
+    let previous_supply = *supply;
+    let added = changes.issuance + changes.withdrawals;
+    let removed = changes.burnt_fees + changes.blob_fees_burnt;
+    let Some(next_supply) = (previous_supply + added).checked_sub(removed) else {
+        bail!(
+            "The supply would become negative at block {}; the initial supply is probably wrong",
+            changes.block_number
+        );
+    };
+    *supply = next_supply;
+
+    let supply_delta = if next_supply >= previous_supply {
+        (next_supply - previous_supply).to_string()
+    } else {
+        format!("-{}", previous_supply - next_supply)
+    };
+
+    Ok(EthSupply {
+        block_number: changes.block_number,
+        block_hash: changes.block_hash.clone(),
+        issuance: changes.issuance.to_string(),
+        burnt_fees: changes.burnt_fees.to_string(),
+        withdrawals: changes.withdrawals.to_string(),
+        blob_fees_burnt: changes.blob_fees_burnt.to_string(),
+        supply_delta,
+        supply: next_supply.to_string(),
+    })
+}
+
+/// The block number and supply of the last row of a supply file, from which a later run
+/// continues. Returns `None` when the file does not exist or has no rows yet.
+///
+/// Rows are appended after the last one, so the file must end with a complete line, every row
+/// must parse and the blocks must follow each other. A file cut off mid-row fails instead of
+/// resuming from a partial row.
+pub fn read_supply_checkpoint(path: &Path) -> Result<Option<(u64, U256)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read(path)
+        .with_context(|| format!("Failed to read supply checkpoint from {}", path.display()))?;
+    if !content.is_empty() && !content.ends_with(b"\n") {
+        bail!("{} ends with an incomplete row; remove it before resuming", path.display());
+    }
+
+    let mut reader = csv::Reader::from_reader(content.as_slice());
+    let mut checkpoint: Option<(u64, U256)> = None;
+    for (index, record) in reader.deserialize::<HashMap<String, String>>().enumerate() {
+        let record = record.with_context(|| format!("Invalid row {} in {}", index + 1, path.display()))?;
+        let (block_number, supply) = parse_checkpoint_row(&record, path)?;
+        if let Some((previous_block_number, _)) = checkpoint {
+            if previous_block_number.checked_add(1) != Some(block_number) {
+                bail!(
+                    "Block {} follows block {} in {}; expected consecutive blocks",
+                    block_number,
+                    previous_block_number,
+                    path.display()
+                );
+            }
+        }
+        checkpoint = Some((block_number, supply));
+    }
+
+    Ok(checkpoint)
+}
+
+fn parse_checkpoint_row(record: &HashMap<String, String>, path: &Path) -> Result<(u64, U256)> {
+    let (Some(block_number), Some(supply)) = (record.get("block_number"), record.get("supply")) else {
+        bail!("{} needs block_number and supply columns", path.display());
+    };
+    let block_number = block_number
+        .parse()
+        .with_context(|| format!("Invalid block number {} in {}", block_number, path.display()))?;
+    let supply = U256::from_dec_str(supply)
+        .with_context(|| format!("Invalid supply {} in {}", supply, path.display()))?;
+
+    Ok((block_number, supply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const HEADER: &str = "block_number,block_hash,issuance,burnt_fees,withdrawals,blob_fees_burnt,supply_delta,supply\n";
+
+    fn changes(issuance: u64, burnt_fees: u64, withdrawals: u64, blob_fees_burnt: u64) -> SupplyChanges {
+        SupplyChanges {
+            block_number: 19_426_587,
+            block_hash: "0x".to_string(),
+            issuance: issuance.into(),
+            burnt_fees: burnt_fees.into(),
+            withdrawals: withdrawals.into(),
+            blob_fees_burnt: blob_fees_burnt.into(),
+        }
+    }
+
+    /// Reads the checkpoint of a supply file with the given content.
+    fn checkpoint(content: &str) -> Result<Option<(u64, U256)>> {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("supply.csv");
+        std::fs::write(&path, content).unwrap();
+        read_supply_checkpoint(&path)
+    }
+
+    #[test]
+    fn adds_issuance_and_withdrawals() {
+        let mut supply = U256::from_dec_str("72009990499480000000000000").unwrap();
+        let mut block_1 = changes(5_000_000_000_000_000_000, 0, 0, 0);
+        block_1.block_number = 1;
+
+        let row = apply_supply_changes(&mut supply, &block_1).unwrap();
+
+        assert_eq!(row.block_number, 1);
+        assert_eq!(row.issuance, "5000000000000000000");
+        assert_eq!(row.supply_delta, "5000000000000000000");
+        assert_eq!(row.supply, "72009995499480000000000000");
+        assert_eq!(supply.to_string(), row.supply);
+    }
+
+    #[test]
+    fn signs_a_shrinking_supply() {
+        let mut supply = U256::from_dec_str("120000000000000000000000000").unwrap();
+
+        let row = apply_supply_changes(
+            &mut supply,
+            &changes(0, 2_000_000_000_000_000, 1_500_000_000_000_000, 500_000_000_000_000),
+        )
+        .unwrap();
+
+        assert_eq!(row.burnt_fees, "2000000000000000");
+        assert_eq!(row.withdrawals, "1500000000000000");
+        assert_eq!(row.blob_fees_burnt, "500000000000000");
+        assert_eq!(row.supply_delta, "-1000000000000000");
+        assert_eq!(row.supply, "119999999999000000000000000");
+    }
+
+    #[test]
+    fn rejects_a_negative_supply() {
+        let mut supply = U256::from(1);
+
+        assert!(apply_supply_changes(&mut supply, &changes(0, 2, 0, 0)).is_err());
+        assert_eq!(supply, U256::from(1));
+    }
+
+    #[test]
+    fn resumes_after_the_last_row() {
+        let complete = format!("{}0,0xd4e5,0,0,0,0,0,100\n1,0x88e9,5,0,0,0,5,105\n", HEADER);
+
+        assert_eq!(checkpoint(&complete).unwrap(), Some((1, U256::from(105))));
+        assert_eq!(checkpoint(HEADER).unwrap(), None);
+        let missing = TempDir::new().unwrap().path().join("supply.csv");
+        assert_eq!(read_supply_checkpoint(&missing).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_a_torn_or_inconsistent_file() {
+        let torn = format!("{}0,0xd4e5,0,0,0,0,0,100\n1,0x88e9,5,0,0,0,5,10", HEADER);
+        let gap = format!("{}0,0xd4e5,0,0,0,0,0,100\n2,0xb495,5,0,0,0,5,105\n", HEADER);
+        let invalid = format!("{}0,0xd4e5,0,0,0,0,0,1x0\n1,0x88e9,5,0,0,0,5,105\n", HEADER);
+        let short = format!("{}0,0xd4e5,0,0\n1,0x88e9,5,0,0,0,5,105\n", HEADER);
+
+        assert!(checkpoint(&torn).is_err());
+        assert!(checkpoint(&gap).is_err());
+        assert!(checkpoint(&invalid).is_err());
+        assert!(checkpoint(&short).is_err());
+    }
+}
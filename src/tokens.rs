@@ -0,0 +1,250 @@
+use crate::cache::DiskCache;
+use crate::domain::{Token, TokenBalance, TokenMetadata};
+use anyhow::{Context, Result};
+use ethers::abi::{decode, encode, ParamType, Token as AbiToken};
+use ethers::providers::{JsonRpcError, Middleware, MiddlewareError};
+use ethers::types::{Address, Bytes, TransactionRequest, U256};
+use tracing::debug;
+
+const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03]; // name()
+const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41]; // symbol()
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67]; // decimals()
+const TOTAL_SUPPLY_SELECTOR: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd]; // totalSupply()
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31]; // balanceOf(address)
+const AGGREGATE3_SELECTOR: [u8; 4] = [0x82, 0xad, 0x56, 0xcb]; // aggregate3((address,bool,bytes)[])
+
+/// JSON-RPC code geth and most clients give a reverted `eth_call`.
+const EXECUTION_REVERTED_CODE: i64 = 3;
+
+/// Multicall3 is deployed at the same address on mainnet and most other chains.
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+/// Reads ERC-20/ERC-721 token metadata with `eth_call`.
+///
+/// `name`, `symbol` and `decimals` are cached per address and `totalSupply` per address and
+/// block, so a cache file shared between runs avoids calling the same token twice. Metadata
+/// without any field is not cached, since a token read before its deployment block has none.
+#[derive(Clone)]
+pub struct TokenService<M> {
+    provider: M,
+    cache: DiskCache<TokenMetadata>,
+}
+
+impl<M> TokenService<M>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    pub fn new(provider: M, cache: DiskCache<TokenMetadata>) -> Self {
+        Self { provider, cache }
+    }
+
+    pub async fn get_token(&self, address: Address, block_number: u64) -> Result<Token> {
+        let cache_key = format!("{:#x}", address);
+        let mut metadata = match self.cache.get(&cache_key) {
+            Some(metadata) => metadata,
+            None => TokenMetadata {
+                name: self.call_string(address, NAME_SELECTOR, block_number).await?,
+                symbol: self.call_string(address, SYMBOL_SELECTOR, block_number).await?,
+                decimals: self
+                    .call_uint(address, DECIMALS_SELECTOR, block_number)
+                    .await?
+                    .filter(|decimals| decimals.bits() <= 64)
+                    .map(|decimals| decimals.as_u64()),
+                total_supply: Default::default(),
+            },
+        };
+
+        let total_supply = match metadata.total_supply.get(&block_number) {
+            Some(total_supply) => total_supply.clone(),
+            None => {
+                let total_supply = self
+                    .call_uint(address, TOTAL_SUPPLY_SELECTOR, block_number)
+                    .await?
+                    .map(|total_supply| total_supply.to_string());
+                metadata.total_supply.insert(block_number, total_supply.clone());
+                total_supply
+            }
+        };
+
+        if metadata.name.is_some() || metadata.symbol.is_some() || metadata.decimals.is_some() {
+            self.cache.insert(cache_key.clone(), metadata.clone());
+        }
+
+        Ok(Token {
+            address: cache_key,
+            symbol: metadata.symbol,
+            name: metadata.name,
+            decimals: metadata.decimals,
+            total_supply,
+            block_number,
+        })
+    }
+
+    pub fn save_cache(&self) -> Result<(), std::io::Error> {
+        self.cache.save()
+    }
+
+    async fn call(&self, address: Address, selector: [u8; 4], block_number: u64) -> Result<Option<Bytes>> {
+        call_view(&self.provider, address, selector.to_vec(), block_number).await
+    }
+
+    async fn call_string(&self, address: Address, selector: [u8; 4], block_number: u64) -> Result<Option<String>> {
+        Ok(self
+            .call(address, selector, block_number)
+            .await?
+            .and_then(|output| decode_string(&output)))
+    }
+
+    async fn call_uint(
+        &self,
+        address: Address,
+        selector: [u8; 4],
+        block_number: u64,
+    ) -> Result<Option<U256>> {
+        Ok(self
+            .call(address, selector, block_number)
+            .await?
+            .and_then(|output| decode_uint(&output)))
+    }
+}
+
+/// Calls a view function. Failed executions and empty results become `None`; any other error,
+/// such as a rate limit or a pruned state, is returned.
+pub async fn call_view<M>(provider: &M, address: Address, data: Vec<u8>, block_number: u64) -> Result<Option<Bytes>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let tx = TransactionRequest::new().to(address).data(data).into();
+
+    match provider.call(&tx, Some(block_number.into())).await {
+        Ok(output) if output.is_empty() => Ok(None),
+        Ok(output) => Ok(Some(output)),
+        Err(e) if e.as_error_response().is_some_and(is_execution_failure) => {
+            debug!("Call to {:#x} failed: {}", address, e);
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether an error response reports a failed execution rather than a failed request: a revert,
+/// or the invalid opcode or jump with which contracts compiled before Byzantium throw.
+fn is_execution_failure(error: &JsonRpcError) -> bool {
+    let message = error.message.to_lowercase();
+    error.code == EXECUTION_REVERTED_CODE
+        || message.contains("revert")
+        || message.contains("invalid opcode")
+        || message.contains("invalid jump")
+}
+
+/// Whether Multicall3 has been deployed at `block_number`.
+pub async fn is_multicall3_deployed<M>(provider: &M, block_number: u64) -> Result<bool>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let multicall3: Address = MULTICALL3_ADDRESS.parse()?;
+    let code = provider.get_code(multicall3, Some(block_number.into())).await?;
+    Ok(!code.is_empty())
+}
+
+/// Reads `balanceOf(holder)` for every (holder, token) pair at `block_number`.
+///
+/// With `use_multicall3` all calls go into a single Multicall3 `aggregate3` call that allows
+/// individual failures; otherwise each balance is a separate `eth_call`. Failed calls leave the
+/// balance empty.
+pub async fn fetch_token_balances<M>(
+    provider: &M,
+    pairs: &[(Address, Address)],
+    block_number: u64,
+    use_multicall3: bool,
+) -> Result<Vec<TokenBalance>>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    let balances = if use_multicall3 {
+        let calls = pairs
+            .iter()
+            .map(|(holder, token)| {
+                AbiToken::Tuple(vec![
+                    AbiToken::Address(*token),
+                    AbiToken::Bool(true),
+                    AbiToken::Bytes(balance_of_call_data(*holder)),
+                ])
+            })
+            .collect();
+        let data = [&AGGREGATE3_SELECTOR[..], &encode(&[AbiToken::Array(calls)])].concat();
+
+        let output = call_view(provider, MULTICALL3_ADDRESS.parse()?, data, block_number)
+            .await?
+            .context("Multicall3 aggregate3 call failed")?;
+        let result_type = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])));
+        let results = decode(&[result_type], &output)?
+            .into_iter()
+            .next()
+            .and_then(AbiToken::into_array)
+            .context("Malformed aggregate3 result")?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                let mut fields = result.into_tuple()?.into_iter();
+                let success = fields.next()?.into_bool()?;
+                let return_data = fields.next()?.into_bytes()?;
+                if success {
+                    decode_uint(&return_data)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        let mut balances = Vec::with_capacity(pairs.len());
+        for (holder, token) in pairs {
+            let output = call_view(provider, *token, balance_of_call_data(*holder), block_number).await?;
+            balances.push(output.and_then(|output| decode_uint(&output)));
+        }
+        balances
+    };
+
+    Ok(pairs
+        .iter()
+        .zip(balances)
+        .map(|((holder, token), balance)| TokenBalance {
+            block_number,
+            token_address: format!("{:#x}", token),
+            holder_address: format!("{:#x}", holder),
+            balance: balance.map(|balance| balance.to_string()),
+        })
+        .collect())
+}
+
+fn balance_of_call_data(holder: Address) -> Vec<u8> {
+    [&BALANCE_OF_SELECTOR[..], &encode(&[AbiToken::Address(holder)])].concat()
+}
+
+fn decode_uint(output: &[u8]) -> Option<U256> {
+    decode(&[ParamType::Uint(256)], output)
+        .ok()?
+        .into_iter()
+        .next()?
+        .into_uint()
+}
+
+/// Decodes an ABI `string`, falling back to the `bytes32` that some older tokens such as MKR
+/// return instead.
+fn decode_string(output: &[u8]) -> Option<String> {
+    let value = match decode(&[ParamType::String], output) {
+        Ok(tokens) => tokens.into_iter().next()?.into_string()?,
+        Err(_) if output.len() == 32 => {
+            let end = output.iter().position(|b| *b == 0).unwrap_or(output.len());
+            String::from_utf8(output[..end].to_vec()).ok()?
+        }
+        Err(_) => return None,
+    };
+
+    Some(value.replace('\0', ""))
+}
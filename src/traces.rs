@@ -0,0 +1,300 @@
+use crate::domain::Trace;
+use ethers::types::{CallFrame, NameOrAddress, H256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Which client family traces and state diffs are read from.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceSource {
+    /// The Parity/Erigon `trace_` namespace
+    Parity,
+    /// Geth's `debug_traceBlockByNumber` tracers, also served by Nethermind and Reth
+    Geth,
+}
+
+/// One entry of a `debug_traceBlockByNumber` response. Older Geth versions omit `txHash`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GethTransactionTrace {
+    #[serde(rename = "txHash", default)]
+    pub tx_hash: Option<H256>,
+    pub result: CallFrame,
+}
+
+/// Flattens a `callTracer` frame tree into the rows `trace_block` would return for the same
+/// transaction: frames are visited depth first and every frame gets the path of child indexes
+/// leading to it as its `trace_address`.
+///
+/// Geth reports no block rewards, so those rows only exist with the Parity source.
+pub fn flatten_call_frame(
+    frame: &CallFrame,
+    block_number: u64,
+    transaction_hash: &str,
+    transaction_index: u64,
+) -> Vec<Trace> {
+    let mut traces = vec![];
+    let mut stack = vec![(frame, vec![])];
+
+    while let Some((frame, trace_address)) = stack.pop() {
+        let calls = frame.calls.as_deref().unwrap_or_default();
+        for (index, call) in calls.iter().enumerate().rev() {
+            let mut child_address = trace_address.clone();
+            child_address.push(index as u64);
+            stack.push((call, child_address));
+        }
+
+        traces.push(call_frame_to_trace(
+            frame,
+            block_number,
+            transaction_hash,
+            transaction_index,
+            trace_address,
+        ));
+    }
+
+    traces
+}
+
+fn call_frame_to_trace(
+    frame: &CallFrame,
+    block_number: u64,
+    transaction_hash: &str,
+    transaction_index: u64,
+    trace_address: Vec<u64>,
+) -> Trace {
+    let frame_type = frame.typ.to_lowercase();
+    let (trace_type, call_type) = match frame_type.as_str() {
+        "create" | "create2" => ("create", None),
+        "selfdestruct" => ("suicide", None),
+        _ => ("call", Some(frame_type.clone())),
+    };
+    let is_suicide = trace_type == "suicide";
+
+    Trace {
+        block_number,
+        transaction_hash: Some(transaction_hash.to_string()),
+        transaction_index: Some(transaction_index),
+        from_address: Some(format!("{:#x}", frame.from)),
+        to_address: frame.to.as_ref().map(|to| match to {
+            NameOrAddress::Address(address) => format!("{:#x}", address),
+            NameOrAddress::Name(name) => name.clone(),
+        }),
+        value: frame.value.unwrap_or_default().to_string(),
+        input: (!is_suicide).then(|| format!("{:#x}", frame.input)),
+        output: frame.output.as_ref().map(|output| format!("{:#x}", output)),
+        trace_type: trace_type.to_string(),
+        call_type,
+        reward_type: None,
+        gas: (!is_suicide).then(|| frame.gas.as_u64()),
+        gas_used: (!is_suicide).then(|| frame.gas_used.as_u64()),
+        subtraces: frame.calls.as_ref().map_or(0, |calls| calls.len() as u64),
+        trace_address,
+        error: frame.error.clone(),
+        status: 1,
+        trace_id: String::new(),
+    }
+}
+
+/// Marks a trace as failed (`status = 0`) when it or any of its ancestors in the same
+/// transaction has an error, since a reverted frame discards the effects of everything below it.
+pub fn calculate_trace_statuses(traces: &mut [Trace]) {
+    let mut by_transaction: HashMap<Option<String>, Vec<&mut Trace>> = HashMap::new();
+    for trace in traces.iter_mut() {
+        trace.status = if trace.error.is_some() { 0 } else { 1 };
+        by_transaction
+            .entry(trace.transaction_hash.clone())
+            .or_default()
+            .push(trace);
+    }
+
+    for (transaction_hash, mut transaction_traces) in by_transaction {
+        if transaction_hash.is_none() {
+            continue;
+        }
+
+        // Parents have shorter trace addresses, so they are resolved before their children
+        transaction_traces.sort_by_key(|trace| trace.trace_address.len());
+        let mut failed_addresses: Vec<Vec<u64>> = vec![];
+        for trace in transaction_traces {
+            let parent_failed = failed_addresses
+                .iter()
+                .any(|failed| trace.trace_address.starts_with(failed));
+            if parent_failed {
+                trace.status = 0;
+            }
+            if trace.status == 0 {
+                failed_addresses.push(trace.trace_address.clone());
+            }
+        }
+    }
+}
+
+/// Assigns ids that stay the same between exports of the same block:
+/// `{trace_type}_{transaction_hash}_{trace_address}` for traces inside a transaction and
+/// `{trace_type}_{block_number}_{index}` for block-level traces such as rewards.
+pub fn calculate_trace_ids(traces: &mut [Trace]) {
+    let mut block_scoped: Vec<&mut Trace> = vec![];
+
+    for trace in traces.iter_mut() {
+        match &trace.transaction_hash {
+            Some(transaction_hash) => {
+                let trace_address = trace
+                    .trace_address
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join("_");
+                trace.trace_id = format!("{}_{}_{}", trace.trace_type, transaction_hash, trace_address);
+            }
+            None => block_scoped.push(trace),
+        }
+    }
+
+    // Block-level traces carry no position, so order them by content to get a stable index
+    block_scoped.sort_by(|a, b| {
+        (&a.reward_type, &a.from_address, &a.to_address, a.value.len(), &a.value).cmp(&(
+            &b.reward_type,
+            &b.from_address,
+            &b.to_address,
+            b.value.len(),
+            &b.value,
+        ))
+    });
+    for (index, trace) in block_scoped.into_iter().enumerate() {
+        trace.trace_id = format!("{}_{}_{}", trace.trace_type, trace.block_number, index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSACTION_HASH: &str = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+
+    fn address(last_byte: u8) -> String {
+        format!("0x{:040x}", last_byte)
+    }
+
+    /// A call into a router that reads a price, makes a swap that reverts together with the
+    /// transfer inside it, and then deploys a contract.
+    fn transaction_trace() -> GethTransactionTrace {
+        serde_json::from_value(serde_json::json!({
+            "txHash": TRANSACTION_HASH,
+            "result": {
+                "type": "CALL", "from": address(1), "to": address(2), "value": "0xde0b6b3a7640000",
+                "gas": "0x30d40", "gasUsed": "0x1d4c0", "input": "0x7ff36ab5", "output": "0x",
+                "calls": [
+                    {
+                        "type": "STATICCALL", "from": address(2), "to": address(3), "gas": "0x2710",
+                        "gasUsed": "0x3e8", "input": "0x0902f1ac", "output": "0x01",
+                    },
+                    {
+                        "type": "CALL", "from": address(2), "to": address(4), "value": "0x0",
+                        "gas": "0x9c40", "gasUsed": "0x9c40", "input": "0x022c0d9f",
+                        "error": "execution reverted",
+                        "calls": [{
+                            "type": "CALL", "from": address(4), "to": address(5), "value": "0x0",
+                            "gas": "0x7530", "gasUsed": "0x1388", "input": "0xa9059cbb", "output": "0x01",
+                        }],
+                    },
+                    {
+                        "type": "CREATE2", "from": address(2), "to": address(6), "value": "0x0",
+                        "gas": "0x4e20", "gasUsed": "0x2710", "input": "0x6080", "output": "0x6080",
+                    },
+                ],
+            },
+        }))
+        .unwrap()
+    }
+
+    fn reward_trace(miner: u8, value: &str) -> Trace {
+        Trace {
+            block_number: 1_000_000,
+            transaction_hash: None,
+            transaction_index: None,
+            from_address: None,
+            to_address: Some(address(miner)),
+            value: value.to_string(),
+            input: None,
+            output: None,
+            trace_type: "reward".to_string(),
+            call_type: None,
+            reward_type: Some("block".to_string()),
+            gas: None,
+            gas_used: None,
+            subtraces: 0,
+            trace_address: vec![],
+            error: None,
+            status: 1,
+            trace_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn flattens_call_frames_depth_first() {
+        let transaction_trace = transaction_trace();
+
+        let traces = flatten_call_frame(&transaction_trace.result, 1_000_000, TRANSACTION_HASH, 7);
+
+        let rows = traces
+            .iter()
+            .map(|trace| {
+                (trace.trace_address.clone(), trace.trace_type.as_str(), trace.call_type.as_deref(), trace.subtraces)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                (vec![], "call", Some("call"), 3),
+                (vec![0], "call", Some("staticcall"), 0),
+                (vec![1], "call", Some("call"), 1),
+                (vec![1, 0], "call", Some("call"), 0),
+                (vec![2], "create", None, 0),
+            ]
+        );
+
+        let root = &traces[0];
+        assert_eq!(root.from_address.as_deref(), Some(address(1).as_str()));
+        assert_eq!(root.to_address.as_deref(), Some(address(2).as_str()));
+        assert_eq!(root.value, "1000000000000000000");
+        assert_eq!(root.gas, Some(200_000));
+        assert_eq!(root.gas_used, Some(120_000));
+        assert_eq!(root.transaction_index, Some(7));
+        assert_eq!(traces[3].to_address.as_deref(), Some(address(5).as_str()));
+        assert_eq!(traces[2].error.as_deref(), Some("execution reverted"));
+    }
+
+    #[test]
+    fn fails_the_children_of_a_reverted_frame() {
+        let transaction_trace = transaction_trace();
+        let mut traces = flatten_call_frame(&transaction_trace.result, 1_000_000, TRANSACTION_HASH, 7);
+
+        calculate_trace_statuses(&mut traces);
+
+        let statuses = traces.iter().map(|trace| trace.status).collect::<Vec<_>>();
+        assert_eq!(statuses, [1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn builds_trace_ids_from_the_transaction_and_trace_address() {
+        let transaction_trace = transaction_trace();
+        let mut traces = flatten_call_frame(&transaction_trace.result, 1_000_000, TRANSACTION_HASH, 7);
+        traces.push(reward_trace(9, "5000000000000000000"));
+        traces.push(reward_trace(8, "4375000000000000000"));
+
+        calculate_trace_ids(&mut traces);
+
+        let trace_ids = traces.iter().map(|trace| trace.trace_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            trace_ids,
+            [
+                format!("call_{}_", TRANSACTION_HASH),
+                format!("call_{}_0", TRANSACTION_HASH),
+                format!("call_{}_1", TRANSACTION_HASH),
+                format!("call_{}_1_0", TRANSACTION_HASH),
+                format!("create_{}_2", TRANSACTION_HASH),
+                "reward_1000000_1".to_string(),
+                "reward_1000000_0".to_string(),
+            ]
+        );
+    }
+}
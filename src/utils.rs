@@ -1,4 +1,6 @@
-use thiserror::Error;
+use anyhow::{Context, Result};
+use ethers::types::Address;
+use std::path::Path;
 use std::time::Instant;
 use tracing::info;
 
@@ -68,17 +70,72 @@ impl ProgressTracker {
     }
 }
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error(transparent)]
-    ProviderError(#[from] crate::providers::ProviderError),
+/// Reads addresses from a file with one address per line, or from the `address` column of a
+/// CSV file with a header such as the output of `export_contracts`.
+pub fn read_addresses(path: &Path) -> Result<Vec<Address>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read addresses from {}", path.display()))?;
 
-    #[error(transparent)]
-    IoError(#[from] std::io::Error),
+    let has_header = content
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| !line.trim().starts_with("0x"));
 
-    #[error(transparent)]
-    CsvError(#[from] csv::Error),
+    let values = if has_header {
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        let column = reader
+            .headers()?
+            .iter()
+            .position(|name| name == "address")
+            .with_context(|| format!("No address column in {}", path.display()))?;
+        reader
+            .records()
+            .map(|record| Ok(record?.get(column).unwrap_or_default().to_string()))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        content.lines().map(|line| line.to_string()).collect()
+    };
 
-    #[error(transparent)]
-    Other(#[from] anyhow::Error),
-}
\ No newline at end of file
+    values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<Address>()
+                .with_context(|| format!("Invalid address {:?} in {}", value, path.display()))
+        })
+        .collect()
+}
+
+/// Reads (holder, token) pairs from a CSV file with `holder_address` and `token_address` columns.
+pub fn read_address_pairs(path: &Path) -> Result<Vec<(Address, Address)>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to read address pairs from {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .with_context(|| format!("No {} column in {}", name, path.display()))
+    };
+    let holder_column = column("holder_address")?;
+    let token_column = column("token_address")?;
+
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<Address>()
+            .with_context(|| format!("Invalid address {:?} in {}", value, path.display()))
+    };
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            let holder = parse(record.get(holder_column).unwrap_or_default())?;
+            let token = parse(record.get(token_column).unwrap_or_default())?;
+            Ok((holder, token))
+        })
+        .collect()
+}